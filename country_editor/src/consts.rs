@@ -1,9 +1,3 @@
 pub const TEMPLATE_FILE: &str = r#"\common\scripted_effects\template_automated_law_setup.txt"#;
-pub const COUNTRIES_FOLDER: &str = r#"\common\history\countries"#;
-pub const LAWS_FOLDER: &str = r#"\common\laws"#;
-pub const LAWS_GROUP_FOLDER: &str = r#"\common\law_groups"#;
 pub const ACTIVATE_LAW_TAG: &str = "activate_law";
-pub const LAW_GROUP_TAG: &str = "group";
 pub const COUNTRIES_TAG: &str = "COUNTRIES";
-pub const LAW_GROUP_CATEGORY_TAG: &str = "law_group_category";
-pub const LAW_TEXTURE_TAG: &str = "icon";
\ No newline at end of file
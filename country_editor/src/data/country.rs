@@ -1,5 +1,6 @@
-use std::{cell::RefCell, collections::HashMap, fmt::{self, Display, Formatter, Write}, rc::Rc, result};
-use vic3_parser::Tree;
+use std::{cell::RefCell, collections::{BTreeMap, HashMap}, fmt::{self, Display, Formatter, Write}, rc::Rc, result};
+use serde::Serialize;
+use vic3_parser::{ser::to_tree, Tree};
 
 use crate::{consts, data::IsCountry, scanner::Scanner};
 
@@ -9,7 +10,17 @@ use super::{law::LawGroup, Data, ScriptedEffectLawsTemplate, WriteAction};
 pub struct Country {
     name: String,
     laws: HashMap<String, (LawSetBy, String)>,
-    scripted_effects: Vec<String>
+    scripted_effects: Vec<String>,
+    // Technologies the history researches, without their prerequisites
+    technologies: Vec<String>
+}
+
+// What gets written in the history of a country, the manual laws and the scripted effects it calls
+#[derive(Serialize)]
+struct CountryLawSetup {
+    activate_law: Vec<String>,
+    #[serde(flatten)]
+    scripted_effects: BTreeMap<String, bool>
 }
 
 #[derive(Debug)]
@@ -29,9 +40,20 @@ impl Country {
         Country {
             name: name.to_string(),
             laws,
-            scripted_effects: Vec::new()
+            scripted_effects: Vec::new(),
+            technologies: Vec::new()
+        }
+    }
+
+    pub fn add_technology(&mut self, technology: &str) {
+        if !self.technologies.iter().any(|t| t == technology) {
+            self.technologies.push(technology.to_string());
         }
     }
+
+    pub fn get_technologies(&self) -> &Vec<String> {
+        &self.technologies
+    }
     pub fn set_law(&mut self, law: &str, group: &str, set_by: LawSetBy) {
         // If the Law is set by a scripted effect, add it to the list of scripted effects
         // Check that it is not already in the list
@@ -58,42 +80,39 @@ impl Country {
         Ok(())
     }
 
-    fn to_tree_found(&self, data: &Data, t: &Tree, f: impl Into<String>) -> WriteAction {
-                // 2. Delete all laws setting in the tree 
-                let mut res = t.delete_children_filtered(|s| s.starts_with(consts::ACTIVATE_LAW_TAG) || {
+    fn to_tree_found(&self, data: &Data, t: &Tree, f: impl Into<String>) -> Result<WriteAction, String> {
+                // 2. Delete all laws setting in a copy of the tree, the scanned tree must stay as it is
+                let mut res = t.deep_clone();
+                let law_settings = res.child_handles(|s| s.starts_with(consts::ACTIVATE_LAW_TAG) || {
                     // Check if there is a scripted effect that is in the list of scripted effects in Data
                     data.get_scripted_effect(s).is_some()
                 });
-        
-                // 3. Add the laws to the tree
-                for (group, (set_by, law)) in &self.laws {
-                    match set_by {
-                        LawSetBy::Default => {
-                            // The law is set by default, so we don't need to add it to the tree
-                        },
-                        LawSetBy::ScriptedEffect(_) => {
-                            // The law is set by a scripted effect, so we need to add it to the tree later
-                        },
-                        LawSetBy::Manual => {
-                            res.add_child(Tree::from_key_value(consts::ACTIVATE_LAW_TAG, &format!("law:{}", law), 0));
-                        }
-                    }
+                for handle in law_settings {
+                    res.remove(handle)?;
                 }
         
-                for scripted_effect in &self.scripted_effects {
-                    if let Some(_) = data.get_scripted_effect(scripted_effect) {
-                        res.add_child(Tree::from_key_value(&scripted_effect, "yes", 0));
-                    }
-                }   
-        
+                // 3. Add the laws to the tree
+                let activate_law = self.laws.values().filter_map(|(set_by, law)| match set_by {
+                    // The law is set by default, so we don't need to add it to the tree
+                    LawSetBy::Default => None,
+                    // The law is set by a scripted effect, which is added below
+                    LawSetBy::ScriptedEffect(_) => None,
+                    LawSetBy::Manual => Some(format!("law:{}", law)),
+                }).collect();
+                let scripted_effects = self.scripted_effects.iter()
+                    .filter(|scripted_effect| data.get_scripted_effect(scripted_effect).is_some())
+                    .map(|scripted_effect| (scripted_effect.clone(), true))
+                    .collect();
+                res.extend_from(&to_tree(&CountryLawSetup { activate_law, scripted_effects })?);
+
                 let mut result_tree = Tree::with_named_root("COUNTRIES");
                 result_tree.add_child_tree(res);
         
-                WriteAction::new(&f.into(), result_tree, IsCountry::Yes(format!("c:{} =", self.name)))
+                Ok(WriteAction::new(&f.into(), result_tree, IsCountry::Yes(format!("c:{} =", self.name))))
 
     }
 
-    pub fn to_tree(&self, scanner: &Scanner, data: &Data) -> WriteAction {
+    pub fn to_tree(&self, scanner: &Scanner, data: &Data) -> Result<WriteAction, String> {
 
         println!("Country: {}", self.to_string());
         // Steps 
@@ -124,7 +143,7 @@ impl Display for Country {
     }
 }
 
-pub fn bulk_to_tree(countries: &[&Country], scanner: &Scanner, data: &Data) -> Vec<WriteAction> {
+pub fn bulk_to_tree(countries: &[&Country], scanner: &Scanner, data: &Data) -> Result<Vec<WriteAction>, String> {
     // This is more efficient than calling to_tree on each country
     // Because to_tree will search for the country in the scanner for each country
     // This way we only search for the country once
@@ -140,8 +159,8 @@ pub fn bulk_to_tree(countries: &[&Country], scanner: &Scanner, data: &Data) -> V
     }).collect();
 
     for (f, c, t) in v {
-        res.push(c.to_tree_found(data, &t, f));
+        res.push(c.to_tree_found(data, &t, f)?);
     }
 
-    res
+    Ok(res)
 }
\ No newline at end of file
@@ -1,8 +1,9 @@
-use std::{cell::RefCell, collections::HashMap, io::Write, rc::Rc};
+use std::{cell::RefCell, collections::{BTreeMap, HashMap}, io::Write, rc::Rc};
 
 pub use country::{Country, LawSetBy, bulk_to_tree};
 pub use law::{Law, LawGroup, ScriptedEffectLawsTemplate};
-use vic3_parser::Tree;
+use serde::Serialize;
+use vic3_parser::{diff::Diff, ser::to_tree, technology::Technologies, Parser, Tree};
 
 use crate::{consts, scanner::Scanner};
 
@@ -13,7 +14,14 @@ pub struct Data {
     countries: HashMap<String, Country>,
     laws: HashMap<String, Law>,
     law_groups: HashMap<String, LawGroup>,
-    scripted_effects: HashMap<String, ScriptedEffectLawsTemplate>
+    scripted_effects: HashMap<String, ScriptedEffectLawsTemplate>,
+    technologies: Technologies
+}
+
+// A scripted effect of the templates file, e.g. template = { activate_law = law:law_x }
+#[derive(Serialize)]
+struct TemplateDefinition {
+    activate_law: Vec<String>
 }
 
 pub struct WriteAction { 
@@ -44,6 +52,23 @@ impl WriteAction {
         &self.content
     }
 
+    // What write would change in the file, compared with what is in it right now
+    // A file that doesn't exist yet is compared with an empty tree
+    pub fn preview(&self) -> Result<Diff, String> {
+        let current = match std::fs::read_to_string(&self.file) {
+            Ok(text) => Parser::new().parse(text),
+            Err(_) => Tree::default(),
+        };
+        // The file has the content block at its root, unless the content is already a root
+        let mut written = Tree::default();
+        if self.content.get_name() == "root" {
+            written = self.content.clone();
+        } else {
+            written.add_child_tree(self.content.clone());
+        }
+        Ok(Diff::new(&current, &written))
+    }
+
     // Consumes the WriteAction and writes the content to the file
 
     pub fn write(self) -> std::io::Result<()> {
@@ -84,7 +109,7 @@ impl Data {
 
     pub fn bulk_to_tree(&self, scanner: &Scanner, countries: Vec<String>) -> Result<Vec<WriteAction>, String> {
         let ctrs = countries.iter().map(|c| self.get_country(c).ok_or(format!("Country {} not found", c))).collect::<Result<Vec<&Country>, String>>()?;
-        Ok(bulk_to_tree(&ctrs, scanner, self))
+        bulk_to_tree(&ctrs, scanner, self)
     }
 
     pub fn bulk_apply_template_to_countries(&mut self, template_name: &str, country_names: &[&str]) {
@@ -103,21 +128,16 @@ impl Data {
         country.apply_template(&scripted_effect, template_name)
     }
 
-    pub fn generate_templates_tree(&self, scanner: &Scanner) -> WriteAction {
-        let mut tree = Tree::default();
-        let mut highest_id = 98;
-        for (name, template) in &self.scripted_effects {
-            let mut template_tree = Tree::with_named_root(&name);
-            for (law, group) in template.get_laws() {
-                template_tree.add_child(Tree::from_key_value(consts::ACTIVATE_LAW_TAG, &format!("law:{}", law), highest_id));
-                highest_id += 1;
-            }
-            tree.add_child_tree(template_tree);
-        }
-        WriteAction::new(&(scanner.get_mod_path() + consts::TEMPLATE_FILE), tree, IsCountry::No)
+    pub fn generate_templates_tree(&self, scanner: &Scanner) -> Result<WriteAction, String> {
+        let templates: BTreeMap<&String, TemplateDefinition> = self.scripted_effects.iter().map(|(name, template)| {
+            let activate_law = template.get_laws().keys().map(|law| format!("law:{}", law)).collect();
+            (name, TemplateDefinition { activate_law })
+        }).collect();
+        let tree = to_tree(&templates)?;
+        Ok(WriteAction::new(&(scanner.get_mod_path() + consts::TEMPLATE_FILE), tree, IsCountry::No))
     }
 
-    pub fn country_to_tree(&self, scanner: &Scanner, country: impl Into<String>) -> WriteAction {
+    pub fn country_to_tree(&self, scanner: &Scanner, country: impl Into<String>) -> Result<WriteAction, String> {
         let country = self.get_country(&country.into()).unwrap();
         country.to_tree(scanner, self)
     }
@@ -130,16 +150,30 @@ impl Data {
         self.law_groups.get(name)
     }
 
+    // Every key that should have a localization entry, as (category, key) pairs
+    pub fn get_localization_keys(&self) -> Vec<(String, String)> {
+        let mut keys = Vec::new();
+        keys.extend(self.countries.keys().map(|key| ("country".to_string(), key.clone())));
+        keys.extend(self.laws.keys().map(|key| ("law".to_string(), key.clone())));
+        keys.extend(self.law_groups.keys().map(|key| ("law_group".to_string(), key.clone())));
+        keys
+    }
+
     pub fn get_scripted_effect(&self, name: &str) -> Option<&ScriptedEffectLawsTemplate> {
         self.scripted_effects.get(name)
     }
 
-    pub fn new(countries: HashMap<String, Country>, laws: HashMap<String, Law>, law_groups: HashMap<String, LawGroup>, scripted_effects: HashMap<String, ScriptedEffectLawsTemplate>) -> Self {
+    pub fn get_technologies(&self) -> &Technologies {
+        &self.technologies
+    }
+
+    pub fn new(countries: HashMap<String, Country>, laws: HashMap<String, Law>, law_groups: HashMap<String, LawGroup>, scripted_effects: HashMap<String, ScriptedEffectLawsTemplate>, technologies: Technologies) -> Self {
         Data {
             countries,
             laws,
             law_groups,
-            scripted_effects
+            scripted_effects,
+            technologies
         }
     }
 }
\ No newline at end of file
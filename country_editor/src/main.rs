@@ -23,7 +23,7 @@ fn rocket() -> _ {
         .manage(data)
         .manage(scanner)        
         .mount("/", routes![web::index, web::get_resource])
-        .mount("/api", routes![web::default_state, web::get_country_name])
+        .mount("/api", routes![web::default_state, web::get_country_name, web::get_languages, web::get_localization, web::missing_localization, web::get_technologies])
         .mount("/css", FileServer::from(relative!("/web/templates/css")))
         .mount("/data", FileServer::from(relative!("/web/data")))
         .mount("/js", FileServer::from(relative!("/web/templates/js")))
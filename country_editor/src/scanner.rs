@@ -1,19 +1,29 @@
-use std::collections::HashMap;
-use vic3_parser::{Tree, utils::*};
+use std::{collections::HashMap, sync::RwLock};
+use vic3_parser::{Tree, utils::*, localization::{self, Localization}};
+use vic3_game_data::{Database, EffectBlock, GameData, COUNTRIES_FOLDER};
 
 use crate::{consts, data::{Country, Data, Law, LawGroup, LawSetBy, ScriptedEffectLawsTemplate}};
 
 pub struct Scanner {
     mod_path: String,
-    game_path: String
+    game_path: String,
+    // Laws, law groups and technologies
+    game: GameData,
+    // The files of the mod only, the countries are read from there since they are written back there
+    mod_files: GameData,
+    // Localizations are only loaded the first time a language is requested
+    localizations: RwLock<HashMap<String, Localization>>
 }
 
 impl Scanner {
     pub fn new() -> Self {
         let (mod_path, game_path) = get_scan_paths();
         Scanner {
+            game: GameData::new(mod_path.clone(), game_path.clone()),
+            mod_files: GameData::new(mod_path.clone(), mod_path.clone()),
             mod_path,
-            game_path
+            game_path,
+            localizations: RwLock::new(HashMap::new())
         }
     }
 
@@ -22,187 +32,132 @@ impl Scanner {
     }
 
     pub fn scan(&self) -> Result<Data, String> {
-        let scripted_effect_tree = self.generate_scripted_effect_tree();
-        let country_tree = self.generate_country_tree();
-        let law_tree = self.generate_law_tree();
-        let law_group_categories = self.generate_law_group_categories();
+        let (law_compendium, default_laws) = self.generate_law_compendium();
+        let scripted_effects = self.generate_scripted_effects(&law_compendium);
 
-        let (law_compendium, default_laws) = self.generate_law_compendium(&law_tree, &law_group_categories);
-        let scripted_effects = self.generate_scripted_effects(&scripted_effect_tree, &law_compendium);
+        let countries: HashMap<String, Country> = self.generate_countries(&law_compendium, &default_laws, &scripted_effects);
 
-        let countries: HashMap<String, Country> = self.generate_countries(&country_tree, &law_compendium, &default_laws, &scripted_effects);
+        let technologies = self.game.get_technologies();
+        for issue in technologies.validate() {
+            println!("Error in technologies: {}", issue);
+        }
 
-        Ok(Data::new(countries, law_compendium, default_laws, scripted_effects))
+        Ok(Data::new(countries, law_compendium, default_laws, scripted_effects, technologies.clone()))
     }
 
-    fn generate_scripted_effect_tree(&self) -> Tree {
-        let scripted_effect_files = vec![self.mod_path.clone() + consts::TEMPLATE_FILE];        
-        generate_tree(scripted_effect_files)
+    // Only the templates of the editor, the other scripted effects of the mod aren't written back
+    fn read_templates(&self) -> Database<EffectBlock> {
+        let tree = generate_tree(vec![self.mod_path.clone() + consts::TEMPLATE_FILE]);
+        Database::from_tree(&tree, |tree| Ok(EffectBlock::from_tree(tree)))
     }
 
     pub fn countries_per_file(&self) -> Vec<(String, Tree)> { // Path of file & Tree with COUNTRIES moved up to root 
-        let country_files = get_paths(&self.mod_path, &self.mod_path, consts::COUNTRIES_FOLDER);
+        let country_files = get_paths(&self.mod_path, &self.mod_path, COUNTRIES_FOLDER);
         let country_trees = generate_trees_and_remember_files(country_files);
 
         country_trees.into_iter().filter_map(|(file_name, tree)| {
             Some((file_name, tree.move_up(consts::COUNTRIES_TAG)))
         }).collect()
     }
-    fn generate_country_tree(&self) -> Tree {
-        let country_files = get_paths(&self.mod_path, &self.mod_path, consts::COUNTRIES_FOLDER);
-        let binding = generate_trees_and_remember_files(country_files);
-
-        for (x, y) in &binding {
-            //println!("{}", x);
-            if x.contains("swe") || x.contains("can") {
-                //println!("{}: {}", x, y.to_string());
-                //println!("{}", y.move_up(consts::COUNTRIES_TAG).to_string());
-                //println!("{:?}", y.move_up(consts::COUNTRIES_TAG).search_child(|s| s.contains("c:") ));
-            }
-        }
-
-
-        let valid_trees: Vec<Tree> = binding.into_iter().map(|(_, tree)| tree)
-            .filter_map(|tree| {
-                tree.move_up(consts::COUNTRIES_TAG).search_child(|s| {
-                    s.contains("c:")
-                })
-            })
-            .collect();
+    pub fn get_game_path(&self) -> String {
+        self.game_path.clone()
+    }
 
-        Tree::from_children(&valid_trees, 9999)
+    pub fn get_languages(&self) -> Vec<String> {
+        localization::available_languages(&self.mod_path, &self.game_path)
     }
 
-    fn generate_law_tree(&self) -> Tree {
-        let law_files = get_paths(&self.mod_path, &self.game_path, consts::LAWS_FOLDER);
-        generate_tree(law_files)
+    pub fn get_country_display_name(&self, country_code: impl Into<String>, language: &str) -> String {
+        self.get_display_name(&country_code.into(), language)
     }
 
-    fn generate_law_group_categories(&self) -> HashMap<String, String> {
-        let law_group_files = get_paths(&self.mod_path, &self.game_path, consts::LAWS_GROUP_FOLDER);
-        let law_group_tree = generate_tree(law_group_files);
-        let mut law_group_categories = HashMap::new();
-        for child in law_group_tree {
-            if let Ok(category_tree) = child.get(consts::LAW_GROUP_CATEGORY_TAG) {
-                if let Ok(category) = category_tree.value() {
-                    law_group_categories.insert(child.get_name(), category.clone());
-                }
-            }
-        }
-        law_group_categories
+    // Display name of any key (law, law group, country tag...) in the given language
+    // Falls back to the key itself when there is no localization for it
+    pub fn get_display_name(&self, key: &str, language: &str) -> String {
+        self.with_localization(language, |localization| localization.localize(key))
     }
 
-    pub fn get_game_path(&self) -> String {
-        self.game_path.clone()
+    pub fn get_all_localizations(&self) -> Vec<Localization> {
+        localization::load_all(&self.mod_path, &self.game_path)
     }
 
-    pub fn get_country_display_name(&self, country_code: impl Into<String>) -> String {
-        let country_code = country_code.into();
-        let local_file = self.mod_path.clone() + r#"/localization/english/countries_l_english.yml"#;
-        // get the display name of the country
-        // by using serde_yaml::from_str
-        // and then searching for the country code
-        // in the resulting hashmap
-        let file_content = std::fs::read_to_string(local_file).unwrap();
-        let map: HashMap<String, HashMap<String, String>> = serde_yaml::from_str(&file_content).unwrap();
-
-        map.get("l_english").and_then(|m| m.get(&country_code)).unwrap_or(&"N/A".to_string()).to_owned()
+    pub fn with_localization<T>(&self, language: &str, f: impl FnOnce(&Localization) -> T) -> T {
+        if let Some(localization) = self.localizations.read().unwrap().get(language) {
+            return f(localization);
+        }
+        let localization = Localization::load(&self.mod_path, &self.game_path, language);
+        let result = f(&localization);
+        self.localizations.write().unwrap().insert(language.to_string(), localization);
+        result
     }
 
-    fn generate_law_compendium(
-        &self,
-        law_tree: &Tree,
-        law_group_categories: &HashMap<String, String>,
-    ) -> (HashMap<String, Law>, HashMap<String, LawGroup>) {
+    fn generate_law_compendium(&self) -> (HashMap<String, Law>, HashMap<String, LawGroup>) {
         let mut law_compendium = HashMap::new();
         let mut default_laws = HashMap::new();
 
-        for child in law_tree.clone() {
-            if let Ok(group_tree) = child.get(consts::LAW_GROUP_TAG) {
-                if let Ok(group) = group_tree.value() {
-                    if let Some(law_name) = child.get_name().split(':').last() {
-                        if let Ok(texture) = child.get(consts::LAW_TEXTURE_TAG).map(|s| s.value().unwrap_or("N/A".to_string())) {
-                            let law = Law::new(
-                                law_name.to_string(),
-                                group.clone(),
-                                texture.to_string()
-                            );
-                            law_compendium.insert(law_name.to_string(), law.clone());
-                            default_laws
-                                .entry(group.clone())
-                                .or_insert(LawGroup::new(group.clone(), law_group_categories.get(&group).unwrap_or(&"N/A".to_owned()).clone()))
-                                .push(law);
-                        }
-                    }
-                }
-            }
+        // Backwards, the default law of a group is the last one pushed, i.e. the first one of the files
+        for (law_name, definition) in self.game.get_laws().iter().rev() {
+            let group = definition.get_group().to_string();
+            let law = Law::new(
+                law_name.to_string(),
+                group.clone(),
+                definition.get_icon()
+            );
+            law_compendium.insert(law_name.to_string(), law.clone());
+            let category = self.game.get_law_groups().get(&group).map(|law_group| law_group.get_category()).unwrap_or("N/A");
+            default_laws
+                .entry(group.clone())
+                .or_insert(LawGroup::new(group.clone(), category))
+                .push(law);
         }
         (law_compendium, default_laws)
     }
 
-    fn generate_scripted_effects(
-        &self,
-        scripted_effect_tree: &Tree,
-        law_compendium: &HashMap<String, Law>,
-    ) -> HashMap<String, ScriptedEffectLawsTemplate> {
+    fn generate_scripted_effects(&self, law_compendium: &HashMap<String, Law>) -> HashMap<String, ScriptedEffectLawsTemplate> {
         let mut scripted_effects = HashMap::new();
-        for child in scripted_effect_tree.clone() {
-            let mut new_scripted_effect_laws_template = ScriptedEffectLawsTemplate::new();
-            let child_name = child.get_name();
-            for activate_law in child.into_iter_filtered(|s| s.starts_with(consts::ACTIVATE_LAW_TAG)) {
-                if let Ok(raw_law) = activate_law.value() {
-                    let law = raw_law.split(':').last().unwrap().trim().to_owned();
-                    let mut group = "N/A".to_owned();
-                    if let Some(law_in_compendium) = law_compendium.get(&law) {
-                        group = law_in_compendium.get_group().to_string();
-                    }
-                    new_scripted_effect_laws_template.push(Law::new(
-                        law.clone(),
-                        group,
-                        "N/A" // The texture path doesn't matter if it's not in the compendium
-                    ));
-                }
+        for (name, effect) in self.read_templates().iter() {
+            let mut template = ScriptedEffectLawsTemplate::new();
+            for law in effect.get_laws() {
+                let group = law_compendium.get(law).map(|law| law.get_group().to_string()).unwrap_or("N/A".to_owned());
+                // The texture path doesn't matter if it's not in the compendium
+                template.push(Law::new(law.clone(), group, "N/A"));
             }
-            scripted_effects.insert(child_name, new_scripted_effect_laws_template);
+            scripted_effects.insert(name.clone(), template);
         }
         scripted_effects
     }
 
     fn generate_countries(
         &self,
-        country_tree: &Tree,
         law_compendium: &HashMap<String, Law>,
         default_laws: &HashMap<String, LawGroup>,
         scripted_effects: &HashMap<String, ScriptedEffectLawsTemplate>,
     ) -> HashMap<String, Country> {
         let mut countries = HashMap::new();
-        for child in country_tree.clone() {
-            if let Some(country_name) = child.clone().get_name().split(':').last() {
-
-                let mut new_country = Country::from_default(country_name, default_laws);
-
-                // Handle raw law activation
-                for law in child.clone().into_iter_filtered(|s| s.starts_with(consts::ACTIVATE_LAW_TAG)) {
-                    if let Ok(raw_law) = law.value() {
-                        if let Some(law) = raw_law.split(':').last().map(|s| s.trim().to_owned()) {
-                            if let Some(res) = law_compendium.get(&law) {
-                                new_country.set_law(&law,  &res.get_group(), LawSetBy::Manual);
-                            }
-                        }
-                    }
+        for (tag, history) in self.mod_files.get_countries().iter() {
+            let mut new_country = Country::from_default(tag, default_laws);
+
+            // Handle raw law activation
+            for law in history.get_laws() {
+                if let Some(res) = law_compendium.get(law) {
+                    new_country.set_law(law, res.get_group(), LawSetBy::Manual);
                 }
+            }
+
+            // Technologies the country starts with
+            for technology in history.get_technologies() {
+                new_country.add_technology(technology.trim());
+            }
 
-                // Handle scripted effects
-                for scripted_effect in child.into_iter_filtered(|s| !s.starts_with(consts::ACTIVATE_LAW_TAG)) {
-                    let scripted_effect_name = scripted_effect.get_name();
-                    if let Some(scripted_effect_template) = scripted_effects.get(&scripted_effect_name) {
-                        for (law, group) in scripted_effect_template.get_laws() {
-                            new_country.set_law(law, group, LawSetBy::ScriptedEffect(scripted_effect_name.to_string()));
-                        }
+            // Handle scripted effects
+            for call in history.get_calls() {
+                if let Some(scripted_effect_template) = scripted_effects.get(call) {
+                    for (law, group) in scripted_effect_template.get_laws() {
+                        new_country.set_law(law, group, LawSetBy::ScriptedEffect(call.to_string()));
                     }
                 }
-                countries.insert(country_name.to_string(), new_country);
             }
+            countries.insert(tag.to_string(), new_country);
         }
         countries
     }
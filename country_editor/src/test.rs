@@ -27,7 +27,15 @@ fn test() {
     data.apply_template_to_country("test_template", "SWE").expect("Failed to apply template");
 
     
-    //data.country_to_tree(&scanner, "SWE").write().expect("Failed to write country"); 
-    data.bulk_to_tree(&scanner, vec!["SWE".into()]).unwrap().into_iter().map(|s| s.write().expect("Failed to write country")).for_each(drop);
-    data.generate_templates_tree(&scanner).write().expect("Failed to write templates"); 
+    //data.country_to_tree(&scanner, "SWE").unwrap().write().expect("Failed to write country"); 
+    data.bulk_to_tree(&scanner, vec!["SWE".into()]).unwrap().into_iter().map(|s| {
+        // Setting laws only changes the activate_law lines of the country
+        let diff = s.preview().expect("Failed to preview country");
+        assert!(!diff.is_empty());
+        for change in diff.get_changes() {
+            assert_eq!(change.get_path().last().map(|key| key.as_str()), Some("activate_law"), "{}", change);
+        }
+        s.write().expect("Failed to write country")
+    }).for_each(drop);
+    data.generate_templates_tree(&scanner).unwrap().write().expect("Failed to write templates"); 
 }
@@ -1,7 +1,9 @@
+use std::collections::HashMap;
 use std::path::{PathBuf, Path};
 
-use rocket::{fs::NamedFile, State};
+use rocket::{fs::NamedFile, http::ContentType, State};
 use rocket::serde::json::Json;
+use vic3_parser::localization::{MissingKeyReport, DEFAULT_LANGUAGE};
 
 use crate::{consts, dds_converter};
 use crate::data::Data;
@@ -17,9 +19,46 @@ pub async fn get_resource(file: PathBuf) -> Option<NamedFile> {
     NamedFile::open(Path::new("resources/").join(file)).await.ok()
 }
 
-#[get("/countryname/<country>")]
-pub async fn get_country_name(country: String, scanner: &State<Scanner>) -> Json<String> {
-    Json(scanner.get_country_display_name(country))
+#[get("/countryname/<country>?<language>")]
+pub async fn get_country_name(country: String, language: Option<String>, scanner: &State<Scanner>) -> Json<String> {
+    Json(scanner.get_country_display_name(country, &language.unwrap_or(DEFAULT_LANGUAGE.to_owned())))
+}
+
+#[get("/languages")]
+pub async fn get_languages(scanner: &State<Scanner>) -> Json<Vec<String>> {
+    Json(scanner.get_languages())
+}
+
+#[get("/localization/<language>")]
+pub async fn get_localization(language: String, data: &State<Data>, scanner: &State<Scanner>) -> Json<String> {
+    // Display names of everything shown in the editor:
+    /*
+    {
+        "countries": { "SWE": "Sweden", ... },
+        "law_groups": { "lawgroup_economic_system": "Economic System", ... },
+        "laws": { "law_agrarianism": "Agrarianism", ... }
+    }
+     */
+    let names = scanner.with_localization(&language, |localization| {
+        let countries = data.get_countries().into_iter()
+            .map(|country| (country.get_name().to_owned(), localization.localize(country.get_name())))
+            .collect::<HashMap<String, String>>();
+        let law_groups = data.get_law_compendium().values()
+            .map(|law_group| (law_group.get_name().to_owned(), localization.localize(law_group.get_name())))
+            .collect::<HashMap<String, String>>();
+        let laws = data.get_law_compendium().values()
+            .flat_map(|law_group| law_group.get_laws())
+            .map(|law| (law.get_name().to_owned(), localization.localize(law.get_name())))
+            .collect::<HashMap<String, String>>();
+
+        serde_json::json!({
+            "countries": countries,
+            "law_groups": law_groups,
+            "laws": laws
+        })
+    });
+
+    Json(names.to_string())
 }
 
 #[get("/defaultstate")]
@@ -69,6 +108,47 @@ pub async fn default_state(data: &State<Data>, scanner: &State<Scanner>) -> Json
     Json(default_state.to_string())
 }
 
+// Technologies a country starts with, its history's add_technology_researched and their prerequisites
+// in an order they can be researched in
+/*
+{
+    "country": "SWE",
+    "technologies": [
+        { "name": "lathe", "era": 1, "in_history": false },
+        { "name": "mechanical_tools", "era": 1, "in_history": true },
+        ...
+    ]
+}
+ */
+#[get("/technologies/<country>")]
+pub async fn get_technologies(country: String, data: &State<Data>) -> Option<Json<String>> {
+    let researched = data.get_country(&country)?.get_technologies();
+    let technologies = data.get_technologies().research_order(researched).into_iter().map(|name| {
+        let era = data.get_technologies().get(&name).map(|t| t.get_era()).unwrap_or(0);
+        serde_json::json!({
+            "name": name,
+            "era": era,
+            "in_history": researched.contains(&name)
+        })
+    }).collect::<Vec<serde_json::Value>>();
+
+    Some(Json(serde_json::json!({
+        "country": country,
+        "technologies": technologies
+    }).to_string()))
+}
+
+// Countries, laws and law groups that lack a localization entry in some or all languages
+// Returns JSON by default or a plain text report with ?format=text
+#[get("/missinglocalization?<format>")]
+pub async fn missing_localization(format: Option<String>, data: &State<Data>, scanner: &State<Scanner>) -> (ContentType, String) {
+    let report = MissingKeyReport::new(&data.get_localization_keys(), &scanner.get_all_localizations());
+    match format.as_deref() {
+        Some("text") => (ContentType::Plain, report.to_text()),
+        _ => (ContentType::JSON, report.to_json().to_string())
+    }
+}
+
 fn generate_texture_path(texture_path: &str, scanner: &Scanner) -> String {
     // Steps: Check if the file_name in the path already exists in resource and return it if it does
     // If it doesn't try to find the resource path in either the game files or the mod files
@@ -198,13 +198,50 @@ async function load() {
     }
 
     for (let country of default_data.countries) {
-        let countryName = await fetch(`/api/countryname/${country}`).then(response => response.json());
-        pairs.push([country, (countryName == "N/A") ? country : countryName]);
+        pairs.push([country, country]);
     }
 
     document.getElementById("io4cj").style.display = "none";
 
     populateCountryListPairs(pairs);
+
+    await loadLanguages();
+}
+
+async function loadLanguages() {
+    let languages = await fetch("/api/languages").then(response => response.json());
+    let languageSelector = document.getElementById("language-selector");
+    languageSelector.innerHTML = '';
+    for (let language of languages) {
+        let opt = document.createElement('option');
+        opt.value = language;
+        opt.text = capitalize(language.replaceAll("_", " "));
+        languageSelector.appendChild(opt);
+    }
+    languageSelector.addEventListener('change', function() {
+        applyLocalization(this.value);
+    });
+    if (languages.length > 0) {
+        await applyLocalization(languageSelector.value);
+    }
+}
+
+// Replaces the displayed names of laws, law groups and countries
+// by their display names in the selected language
+async function applyLocalization(language) {
+    let names = JSON.parse(await fetch(`/api/localization/${language}`).then(response => response.json()));
+
+    for (let lawSelector of document.querySelectorAll('.lawSelector')) {
+        lawSelector.title = names.law_groups[lawSelector.id] ?? toPrettyName(lawSelector.id);
+        for (let opt of lawSelector.options) {
+            opt.text = names.laws[opt.value] ?? toPrettyName(opt.value);
+        }
+    }
+
+    for (let label of document.querySelectorAll('.dropdown-list label')) {
+        let input = label.querySelector('input');
+        label.lastChild.textContent = ` ${names.countries[input.value] ?? input.value}`;
+    }
 }
 
 function capitalize(name) {
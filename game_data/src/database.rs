@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+use vic3_parser::{de::from_tree, Tree};
+
+// Every entry of a folder, e.g. common/goods, parsed into T
+// The entries that can't be parsed are kept aside with their error instead of failing the whole folder
+#[derive(Debug, Clone)]
+pub struct Database<T> {
+    entries: HashMap<String, T>,
+    // In the order the game loads the files (see discriminate), the save refers to some entries by their position
+    order: Vec<String>,
+    // (entry, error)
+    errors: Vec<(String, String)>,
+}
+
+impl<T> Database<T> {
+    pub fn new() -> Database<T> {
+        Database { entries: HashMap::new(), order: Vec::new(), errors: Vec::new() }
+    }
+
+    // Parses every child of the tree
+    pub fn from_tree(tree: &Tree, parse: impl Fn(&Tree) -> Result<T, String>) -> Database<T> {
+        let mut database = Database::new();
+        // Iterating a tree starts from its last child
+        let children: Vec<Tree> = tree.clone().into_iter().collect();
+        for child in children.into_iter().rev() {
+            match parse(&child) {
+                Ok(entry) => database.insert(child.get_name(), entry),
+                Err(e) => database.errors.push((child.get_name(), e)),
+            }
+        }
+        database
+    }
+
+    // The first entry with a name is kept, the files are in the order the game loads them, see discriminate
+    pub fn insert(&mut self, name: String, entry: T) {
+        if !self.entries.contains_key(&name) {
+            self.order.push(name.clone());
+            self.entries.insert(name, entry);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.entries.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    pub fn get_names(&self) -> &Vec<String> {
+        &self.order
+    }
+
+    // In the order of the files
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&String, &T)> {
+        self.order.iter().map(|name| (name, &self.entries[name]))
+    }
+
+    pub fn get_errors(&self) -> &Vec<(String, String)> {
+        &self.errors
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn into_entries(self) -> HashMap<String, T> {
+        self.entries
+    }
+}
+
+impl<T: DeserializeOwned> Database<T> {
+    pub fn deserialize(tree: &Tree) -> Database<T> {
+        Database::from_tree(tree, from_tree)
+    }
+}
+
+impl<T> Default for Database<T> {
+    fn default() -> Self {
+        Database::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde::Deserialize;
+    use vic3_parser::utils::{discriminate, generate_tree};
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Good {
+        cost: i32,
+    }
+
+    #[test]
+    fn order_of_the_files() {
+        let root = std::env::temp_dir().join(format!("vic3_database_{}", std::process::id()));
+        let (game, mod_root) = (root.join("game"), root.join("mod"));
+        fs::create_dir_all(&game).unwrap();
+        fs::create_dir_all(&mod_root).unwrap();
+        // Written in the opposite order of their names
+        fs::write(game.join("10_luxury.txt"), "wine = { cost = 40 } fruit = { cost = 30 }").unwrap();
+        fs::write(game.join("00_goods.txt"), "grain = { cost = 20 } tools = { cost = 40 }").unwrap();
+        fs::write(mod_root.join("05_mod_goods.txt"), "wine = { cost = 50 } steel = { cost = 50 }").unwrap();
+
+        let load = || {
+            let files = discriminate(mod_root.to_string_lossy().into_owned(), game.to_string_lossy().into_owned());
+            Database::<Good>::deserialize(&generate_tree(files))
+        };
+        let (first, second) = (load(), load());
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(first.get_names(), &["grain", "tools", "wine", "steel", "fruit"]);
+        assert_eq!(first.get_names(), second.get_names());
+        // The first file with the entry wins
+        assert_eq!(first.get("wine").unwrap().cost, 50);
+    }
+}
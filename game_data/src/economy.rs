@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+// A single entry of common/goods
+#[derive(Debug, Clone, Deserialize)]
+pub struct Good {
+    cost: i32,
+    #[serde(default)]
+    category: String,
+}
+
+impl Good {
+    pub fn get_cost(&self) -> i32 {
+        self.cost
+    }
+
+    // staple, industrial, luxury or military
+    pub fn get_category(&self) -> &str {
+        &self.category
+    }
+}
+
+// A single entry of common/buildings
+#[derive(Debug, Clone, Deserialize)]
+pub struct Building {
+    #[serde(default)]
+    building_group: String,
+    production_method_groups: Vec<String>,
+    // Either a number or a script value, see GameData::get_script_values
+    required_construction: Option<String>,
+    #[serde(default)]
+    unlocking_technologies: Vec<String>,
+    #[serde(default = "yes")]
+    buildable: bool,
+    #[serde(default = "yes")]
+    expandable: bool,
+    // The level is capped by the resources of the state, e.g. mines
+    #[serde(default)]
+    has_max_level: bool,
+}
+
+fn yes() -> bool {
+    true
+}
+
+impl Building {
+    pub fn get_building_group(&self) -> &str {
+        &self.building_group
+    }
+
+    pub fn get_production_method_groups(&self) -> &Vec<String> {
+        &self.production_method_groups
+    }
+
+    pub fn get_required_construction(&self) -> Option<&String> {
+        self.required_construction.as_ref()
+    }
+
+    pub fn get_unlocking_technologies(&self) -> &Vec<String> {
+        &self.unlocking_technologies
+    }
+
+    pub fn is_buildable(&self) -> bool {
+        self.buildable
+    }
+
+    pub fn is_expandable(&self) -> bool {
+        self.expandable
+    }
+
+    pub fn has_max_level(&self) -> bool {
+        self.has_max_level
+    }
+}
+
+// A single entry of common/building_groups, e.g. bg_mining
+// What is not set is taken from the parent group
+#[derive(Debug, Clone, Deserialize)]
+pub struct BuildingGroup {
+    parent_group: Option<String>,
+    // urban, rural or development
+    category: Option<String>,
+    // urban or rural
+    land_usage: Option<String>,
+    is_subsistence: Option<bool>,
+}
+
+impl BuildingGroup {
+    pub fn get_parent_group(&self) -> Option<&String> {
+        self.parent_group.as_ref()
+    }
+
+    pub fn get_category(&self) -> Option<&String> {
+        self.category.as_ref()
+    }
+
+    pub fn get_land_usage(&self) -> Option<&String> {
+        self.land_usage.as_ref()
+    }
+
+    pub fn is_subsistence(&self) -> Option<bool> {
+        self.is_subsistence
+    }
+}
+
+// A single entry of common/production_method_groups
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProductionMethodGroup {
+    #[serde(default)]
+    production_methods: Vec<String>,
+}
+
+impl ProductionMethodGroup {
+    pub fn get_production_methods(&self) -> &Vec<String> {
+        &self.production_methods
+    }
+}
+
+// A single entry of common/production_methods
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProductionMethod {
+    #[serde(default)]
+    building_modifiers: BuildingModifiers,
+    #[serde(default)]
+    is_default: bool,
+    #[serde(default)]
+    unlocking_technologies: Vec<String>,
+    // One of these laws has to be enacted, when there are any
+    #[serde(default)]
+    unlocking_laws: Vec<String>,
+    // None of these laws can be enacted
+    #[serde(default)]
+    disallowing_laws: Vec<String>,
+}
+
+// Any of the blocks can be missing, it's then the same as an empty block
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct BuildingModifiers {
+    // Scales with the number of levels of the building
+    level_scaled: HashMap<String, f32>,
+    // Scales with how many of the jobs are filled
+    workforce_scaled: HashMap<String, f32>,
+    // Applies once to the whole building, whatever its number of levels
+    unscaled: HashMap<String, f32>,
+}
+
+impl ProductionMethod {
+    pub fn get_building_modifiers(&self) -> &BuildingModifiers {
+        &self.building_modifiers
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.is_default
+    }
+
+    pub fn get_unlocking_technologies(&self) -> &Vec<String> {
+        &self.unlocking_technologies
+    }
+
+    pub fn get_unlocking_laws(&self) -> &Vec<String> {
+        &self.unlocking_laws
+    }
+
+    pub fn get_disallowing_laws(&self) -> &Vec<String> {
+        &self.disallowing_laws
+    }
+}
+
+impl BuildingModifiers {
+    pub fn get_level_scaled(&self) -> &HashMap<String, f32> {
+        &self.level_scaled
+    }
+
+    pub fn get_workforce_scaled(&self) -> &HashMap<String, f32> {
+        &self.workforce_scaled
+    }
+
+    pub fn get_unscaled(&self) -> &HashMap<String, f32> {
+        &self.unscaled
+    }
+
+    // The scopes that count once per level, for a fully staffed level they add up the same way
+    pub fn get_per_level_scopes(&self) -> [&HashMap<String, f32>; 2] {
+        [&self.workforce_scaled, &self.level_scaled]
+    }
+}
@@ -0,0 +1,60 @@
+use vic3_parser::Tree;
+
+// The parts of an effect block the tools understand
+// Used for the c:TAG blocks of common/history/countries and the entries of common/scripted_effects
+#[derive(Debug, Clone, Default)]
+pub struct EffectBlock {
+    // activate_law = law:law_x, without the law: prefix
+    laws: Vec<String>,
+    // add_technology_researched = x, without their prerequisites
+    technologies: Vec<String>,
+    // Every other key, e.g. effect_starting_politics_traditional = yes, some of them are scripted effects
+    calls: Vec<String>,
+}
+
+impl EffectBlock {
+    pub fn new(laws: Vec<String>, technologies: Vec<String>, calls: Vec<String>) -> EffectBlock {
+        EffectBlock { laws, technologies, calls }
+    }
+
+    pub fn from_tree(tree: &Tree) -> EffectBlock {
+        let mut block = EffectBlock::default();
+        // Iterating a tree starts from its last child, the order of the laws matters
+        let children: Vec<Tree> = tree.clone().into_iter().collect();
+        for child in children.into_iter().rev() {
+            let name = child.get_name();
+            match name.as_str() {
+                "activate_law" => {
+                    if let Ok(law) = child.value() {
+                        block.laws.push(law.trim_start_matches("law:").to_string());
+                    }
+                }
+                "add_technology_researched" => {
+                    if let Ok(technology) = child.value() {
+                        block.technologies.push(technology);
+                    }
+                }
+                _ => block.calls.push(name),
+            }
+        }
+        block
+    }
+
+    // Adds the laws and technologies of another block after the ones of this block
+    pub fn extend(&mut self, other: &EffectBlock) {
+        self.laws.extend(other.laws.iter().cloned());
+        self.technologies.extend(other.technologies.iter().cloned());
+    }
+
+    pub fn get_laws(&self) -> &Vec<String> {
+        &self.laws
+    }
+
+    pub fn get_technologies(&self) -> &Vec<String> {
+        &self.technologies
+    }
+
+    pub fn get_calls(&self) -> &Vec<String> {
+        &self.calls
+    }
+}
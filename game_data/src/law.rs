@@ -0,0 +1,33 @@
+use serde::Deserialize;
+
+// A single entry of common/laws
+#[derive(Debug, Clone, Deserialize)]
+pub struct Law {
+    group: String,
+    #[serde(default)]
+    icon: String,
+}
+
+impl Law {
+    pub fn get_group(&self) -> &str {
+        &self.group
+    }
+
+    pub fn get_icon(&self) -> &str {
+        &self.icon
+    }
+}
+
+// A single entry of common/law_groups
+#[derive(Debug, Clone, Deserialize)]
+pub struct LawGroup {
+    // power_structure, economy or human_rights
+    #[serde(default)]
+    law_group_category: String,
+}
+
+impl LawGroup {
+    pub fn get_category(&self) -> &str {
+        &self.law_group_category
+    }
+}
@@ -0,0 +1,234 @@
+// Typed access to the game files, shared by the tools
+// Each folder is only read the first time it is asked for
+mod database;
+mod economy;
+mod law;
+mod history;
+mod pop;
+mod state;
+
+use std::{collections::{HashMap, HashSet}, sync::OnceLock};
+
+use vic3_parser::{utils::{generate_tree, get_paths, get_scan_paths}, Tree};
+
+pub use database::Database;
+pub use economy::{Building, BuildingGroup, BuildingModifiers, Good, ProductionMethod, ProductionMethodGroup};
+pub use law::{Law, LawGroup};
+pub use history::EffectBlock;
+pub use pop::{BuyPackage, PopNeed, PopNeedEntry};
+pub use state::{DiscoverableResource, StateRegion};
+pub use vic3_parser::technology::{Technologies, Technology, TechnologyIssue, TECHNOLOGIES_FOLDER};
+
+pub const GOODS_FOLDER: &str = r#"\common\goods"#;
+pub const BUILDINGS_FOLDER: &str = r#"\common\buildings"#;
+pub const BUILDING_GROUPS_FOLDER: &str = r#"\common\building_groups"#;
+pub const PRODUCTION_METHODS_FOLDER: &str = r#"\common\production_methods"#;
+pub const PRODUCTION_METHOD_GROUPS_FOLDER: &str = r#"\common\production_method_groups"#;
+pub const LAWS_FOLDER: &str = r#"\common\laws"#;
+pub const LAW_GROUPS_FOLDER: &str = r#"\common\law_groups"#;
+pub const COUNTRIES_FOLDER: &str = r#"\common\history\countries"#;
+pub const SCRIPTED_EFFECTS_FOLDER: &str = r#"\common\scripted_effects"#;
+pub const SCRIPT_VALUES_FOLDER: &str = r#"\common\script_values"#;
+pub const POP_NEEDS_FOLDER: &str = r#"\common\pop_needs"#;
+pub const BUY_PACKAGES_FOLDER: &str = r#"\common\buy_packages"#;
+pub const STATE_REGIONS_FOLDER: &str = r#"\map_data\state_regions"#;
+pub const STATES_FOLDER: &str = r#"\common\history\states"#;
+
+// The files of the mod replace the files of the game with the same name
+pub struct GameData {
+    mod_path: String,
+    game_path: String,
+    goods: OnceLock<Database<Good>>,
+    buildings: OnceLock<Database<Building>>,
+    building_groups: OnceLock<Database<BuildingGroup>>,
+    production_methods: OnceLock<Database<ProductionMethod>>,
+    production_method_groups: OnceLock<Database<ProductionMethodGroup>>,
+    laws: OnceLock<Database<Law>>,
+    law_groups: OnceLock<Database<LawGroup>>,
+    technologies: OnceLock<Technologies>,
+    // Tag without the c: prefix -> history of the country
+    countries: OnceLock<Database<EffectBlock>>,
+    scripted_effects: OnceLock<Database<EffectBlock>>,
+    script_values: OnceLock<HashMap<String, f32>>,
+    pop_needs: OnceLock<Database<PopNeed>>,
+    buy_packages: OnceLock<Database<BuyPackage>>,
+    state_regions: OnceLock<Database<StateRegion>>,
+    // State without the s: prefix -> tags of the countries that own some of it at the start
+    state_owners: OnceLock<Database<Vec<String>>>,
+}
+
+impl GameData {
+    pub fn new(mod_path: impl Into<String>, game_path: impl Into<String>) -> GameData {
+        GameData {
+            mod_path: mod_path.into(),
+            game_path: game_path.into(),
+            goods: OnceLock::new(),
+            buildings: OnceLock::new(),
+            building_groups: OnceLock::new(),
+            production_methods: OnceLock::new(),
+            production_method_groups: OnceLock::new(),
+            laws: OnceLock::new(),
+            law_groups: OnceLock::new(),
+            technologies: OnceLock::new(),
+            countries: OnceLock::new(),
+            scripted_effects: OnceLock::new(),
+            script_values: OnceLock::new(),
+            pop_needs: OnceLock::new(),
+            buy_packages: OnceLock::new(),
+            state_regions: OnceLock::new(),
+            state_owners: OnceLock::new(),
+        }
+    }
+
+    // Uses the paths of scan.cfg, asks for them when the file doesn't exist yet
+    pub fn from_scan_config() -> GameData {
+        let (mod_path, game_path) = get_scan_paths();
+        GameData::new(mod_path, game_path)
+    }
+
+    pub fn get_mod_path(&self) -> &str {
+        &self.mod_path
+    }
+
+    pub fn get_game_path(&self) -> &str {
+        &self.game_path
+    }
+
+    // Every file of the folder merged into a single tree
+    pub fn read_folder(&self, folder: &str) -> Tree {
+        generate_tree(get_paths(&self.mod_path, &self.game_path, folder))
+    }
+
+    pub fn get_goods(&self) -> &Database<Good> {
+        self.goods.get_or_init(|| Database::deserialize(&self.read_folder(GOODS_FOLDER)))
+    }
+
+    pub fn get_buildings(&self) -> &Database<Building> {
+        self.buildings.get_or_init(|| Database::deserialize(&self.read_folder(BUILDINGS_FOLDER)))
+    }
+
+    pub fn get_building_groups(&self) -> &Database<BuildingGroup> {
+        self.building_groups.get_or_init(|| Database::deserialize(&self.read_folder(BUILDING_GROUPS_FOLDER)))
+    }
+
+    pub fn get_production_methods(&self) -> &Database<ProductionMethod> {
+        self.production_methods.get_or_init(|| Database::deserialize(&self.read_folder(PRODUCTION_METHODS_FOLDER)))
+    }
+
+    pub fn get_production_method_groups(&self) -> &Database<ProductionMethodGroup> {
+        self.production_method_groups.get_or_init(|| Database::deserialize(&self.read_folder(PRODUCTION_METHOD_GROUPS_FOLDER)))
+    }
+
+    pub fn get_laws(&self) -> &Database<Law> {
+        self.laws.get_or_init(|| Database::deserialize(&self.read_folder(LAWS_FOLDER)))
+    }
+
+    pub fn get_law_groups(&self) -> &Database<LawGroup> {
+        self.law_groups.get_or_init(|| Database::deserialize(&self.read_folder(LAW_GROUPS_FOLDER)))
+    }
+
+    // The technologies that can't be parsed are left out, see Technologies::validate for the ones
+    // that point to them
+    pub fn get_technologies(&self) -> &Technologies {
+        self.technologies.get_or_init(|| {
+            let database: Database<Technology> = Database::deserialize(&self.read_folder(TECHNOLOGIES_FOLDER));
+            Technologies::new(database.into_entries())
+        })
+    }
+
+    pub fn get_countries(&self) -> &Database<EffectBlock> {
+        self.countries.get_or_init(|| {
+            let mut countries = Database::new();
+            // Each file is a COUNTRIES = { c:TAG = { ... } } block
+            let files: Vec<Tree> = self.read_folder(COUNTRIES_FOLDER).into_iter().collect();
+            for file in files.into_iter().rev() {
+                let blocks: Vec<Tree> = file.into_iter().collect();
+                for country in blocks.into_iter().rev() {
+                    if let Some(tag) = country.get_name().strip_prefix("c:") {
+                        countries.insert(tag.to_string(), EffectBlock::from_tree(&country));
+                    }
+                }
+            }
+            countries
+        })
+    }
+
+    pub fn get_scripted_effects(&self) -> &Database<EffectBlock> {
+        self.scripted_effects.get_or_init(|| Database::from_tree(&self.read_folder(SCRIPTED_EFFECTS_FOLDER), |tree| Ok(EffectBlock::from_tree(tree))))
+    }
+
+    // Only the script values that are plain numbers, e.g. construction_cost_medium = 300
+    pub fn get_script_values(&self) -> &HashMap<String, f32> {
+        self.script_values.get_or_init(|| {
+            self.read_folder(SCRIPT_VALUES_FOLDER).into_iter()
+                .filter_map(|value| Some((value.get_name(), value.value().ok()?.parse().ok()?)))
+                .collect()
+        })
+    }
+
+    pub fn get_pop_needs(&self) -> &Database<PopNeed> {
+        self.pop_needs.get_or_init(|| Database::deserialize(&self.read_folder(POP_NEEDS_FOLDER)))
+    }
+
+    pub fn get_buy_packages(&self) -> &Database<BuyPackage> {
+        self.buy_packages.get_or_init(|| Database::deserialize(&self.read_folder(BUY_PACKAGES_FOLDER)))
+    }
+
+    pub fn get_state_regions(&self) -> &Database<StateRegion> {
+        self.state_regions.get_or_init(|| Database::deserialize(&self.read_folder(STATE_REGIONS_FOLDER)))
+    }
+
+    pub fn get_state_owners(&self) -> &Database<Vec<String>> {
+        self.state_owners.get_or_init(|| {
+            let mut owners = Database::new();
+            // Each file is a STATES = { s:STATE = { create_state = { country = c:TAG ... } ... } } block
+            let files: Vec<Tree> = self.read_folder(STATES_FOLDER).into_iter().collect();
+            for file in files.into_iter().rev() {
+                let blocks: Vec<Tree> = file.into_iter().collect();
+                for state in blocks.into_iter().rev() {
+                    let Some(name) = state.get_name().strip_prefix("s:").map(str::to_string) else {
+                        continue;
+                    };
+                    let children: Vec<Tree> = state.into_iter().collect();
+                    let tags: Vec<String> = children.into_iter().rev()
+                        .filter(|child| child.get_name() == "create_state")
+                        .filter_map(|child| child.into_iter().find(|c| c.get_name() == "country")?.value().ok())
+                        .map(|tag| tag.trim_start_matches("c:").to_string())
+                        .collect();
+                    owners.insert(name, tags);
+                }
+            }
+            owners
+        })
+    }
+
+    // The states where the country owns some land at the start, in the order of the files
+    pub fn get_country_states(&self, tag: &str) -> Vec<String> {
+        self.get_state_owners().iter().filter(|(_, tags)| tags.iter().any(|t| t == tag)).map(|(state, _)| state.clone()).collect()
+    }
+
+    // The laws and technologies of the block together with the ones of every scripted effect it calls
+    // The scripted effects come first, the history usually calls them and then overrides some of their laws
+    pub fn resolve_effects(&self, block: &EffectBlock) -> EffectBlock {
+        let mut resolved = EffectBlock::new(Vec::new(), Vec::new(), block.get_calls().clone());
+        self.resolve_into(block, &mut resolved, &mut HashSet::new());
+        resolved
+    }
+
+    // A scripted effect can call other scripted effects, each one is only followed once
+    fn resolve_into<'a>(&'a self, block: &'a EffectBlock, resolved: &mut EffectBlock, called: &mut HashSet<&'a String>) {
+        for call in block.get_calls() {
+            if called.insert(call) {
+                if let Some(effect) = self.get_scripted_effects().get(call) {
+                    self.resolve_into(effect, resolved, called);
+                }
+            }
+        }
+        resolved.extend(block);
+    }
+
+    // The history of the country with its scripted effects followed
+    pub fn get_country_setup(&self, tag: &str) -> Option<EffectBlock> {
+        Some(self.resolve_effects(self.get_countries().get(tag)?))
+    }
+}
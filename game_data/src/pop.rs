@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+// A single entry of common/pop_needs, e.g. popneed_basic_food
+// The goods that can fulfill the need, pops buy some of each of them
+#[derive(Debug, Clone, Deserialize)]
+pub struct PopNeed {
+    // Bought when none of the goods of the entries are available
+    default: String,
+    #[serde(default, rename = "entry")]
+    entries: Vec<PopNeedEntry>,
+}
+
+impl PopNeed {
+    pub fn get_default(&self) -> &String {
+        &self.default
+    }
+
+    pub fn get_entries(&self) -> &Vec<PopNeedEntry> {
+        &self.entries
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PopNeedEntry {
+    goods: String,
+    #[serde(default = "default_weight")]
+    weight: f32,
+    // Share of the need this good can fulfill at most, 1 when not set
+    max_supply_share: Option<f32>,
+    #[serde(default)]
+    min_supply_share: f32,
+}
+
+fn default_weight() -> f32 {
+    1.
+}
+
+impl PopNeedEntry {
+    pub fn get_goods(&self) -> &String {
+        &self.goods
+    }
+
+    pub fn get_weight(&self) -> f32 {
+        self.weight
+    }
+
+    pub fn get_max_supply_share(&self) -> f32 {
+        self.max_supply_share.unwrap_or(1.)
+    }
+
+    pub fn get_min_supply_share(&self) -> f32 {
+        self.min_supply_share
+    }
+}
+
+// A single entry of common/buy_packages, wealth_1 to wealth_99
+#[derive(Debug, Clone, Deserialize)]
+pub struct BuyPackage {
+    // pop need -> amount bought by 10000 pops of this wealth every week
+    #[serde(default)]
+    goods: HashMap<String, f32>,
+}
+
+impl BuyPackage {
+    pub fn get_needs(&self) -> &HashMap<String, f32> {
+        &self.goods
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+// A single entry of map_data/state_regions, e.g. STATE_SVEALAND
+#[derive(Debug, Clone, Deserialize)]
+pub struct StateRegion {
+    #[serde(default)]
+    arable_land: u32,
+    // Building groups that can use the arable land, e.g. bg_wheat_farms
+    #[serde(default)]
+    arable_resources: Vec<String>,
+    // Building group -> levels, e.g. bg_iron_mining = 30
+    #[serde(default)]
+    capped_resources: HashMap<String, u32>,
+    #[serde(default, rename = "resource")]
+    discoverable_resources: Vec<DiscoverableResource>,
+    subsistence_building: Option<String>,
+}
+
+impl StateRegion {
+    pub fn get_arable_land(&self) -> u32 {
+        self.arable_land
+    }
+
+    pub fn get_arable_resources(&self) -> &Vec<String> {
+        &self.arable_resources
+    }
+
+    pub fn get_capped_resources(&self) -> &HashMap<String, u32> {
+        &self.capped_resources
+    }
+
+    pub fn get_discoverable_resources(&self) -> &Vec<DiscoverableResource> {
+        &self.discoverable_resources
+    }
+
+    pub fn get_subsistence_building(&self) -> Option<&String> {
+        self.subsistence_building.as_ref()
+    }
+}
+
+// resource = { type = bg_gold_fields undiscovered_amount = 5 }
+// Found during the game, e.g. gold or oil
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiscoverableResource {
+    #[serde(rename = "type")]
+    building_group: String,
+    // The building group once the resource runs out, e.g. bg_gold_mining
+    depleted_type: Option<String>,
+    #[serde(default)]
+    discovered_amount: u32,
+    #[serde(default)]
+    undiscovered_amount: u32,
+}
+
+impl DiscoverableResource {
+    pub fn get_building_group(&self) -> &String {
+        &self.building_group
+    }
+
+    pub fn get_depleted_type(&self) -> Option<&String> {
+        self.depleted_type.as_ref()
+    }
+
+    pub fn get_discovered_amount(&self) -> u32 {
+        self.discovered_amount
+    }
+
+    pub fn get_undiscovered_amount(&self) -> u32 {
+        self.undiscovered_amount
+    }
+}
@@ -0,0 +1,410 @@
+use std::fmt::{self, Display};
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+
+use crate::{node::Node, Tree};
+
+// Deserializes a Tree into any type implementing Deserialize
+// Repeated keys can be read as a Vec, yes/no as bool, and fields that are not in the type are ignored
+//
+// #[derive(Deserialize)]
+// struct Good { cost: i32 }
+// let goods: HashMap<String, Good> = from_tree(&goods_tree)?;
+pub fn from_tree<T: DeserializeOwned>(tree: &Tree) -> Result<T, String> {
+    let value = Value::Keyed(vec![tree.root().clone()]);
+    T::deserialize(value).map_err(|e| format!("{}: {}", tree.get_name(), e))
+}
+
+#[derive(Debug)]
+pub struct Error(pub(crate) String);
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl From<Error> for String {
+    fn from(error: Error) -> Self {
+        error.0
+    }
+}
+
+// What is being deserialized
+enum Value {
+    // Every node with the same key in a block, usually only one
+    // key = value is a scalar, key = { ... } is a block
+    Keyed(Vec<Node>),
+    // A bare token inside of a list, e.g. the pms of production_methods = { pm_a pm_b }
+    Token(String),
+}
+
+impl Value {
+    // The last node wins when a key is defined more than once and a single value is expected
+    fn node(&self) -> Option<&Node> {
+        match self {
+            Value::Keyed(nodes) => nodes.last(),
+            Value::Token(_) => None,
+        }
+    }
+
+    fn scalar(&self) -> Option<String> {
+        match self {
+            Value::Token(token) => Some(token.clone()),
+            Value::Keyed(_) => {
+                let children = self.node()?.children();
+                if children.len() == 1 && children[0].children().is_empty() && !children[0].name().is_empty() {
+                    Some(children[0].name())
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    fn expect_scalar(&self) -> Result<String, Error> {
+        self.scalar()
+            .map(|s| unquote(&s))
+            .ok_or_else(|| Error(format!("Expected a value, found a block in {}", self.name())))
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Value::Keyed(nodes) => nodes.last().map(|n| n.name()).unwrap_or_default(),
+            Value::Token(token) => token.clone(),
+        }
+    }
+
+    // A block where every child is a bare token or an anonymous block
+    fn is_list(node: &Node) -> bool {
+        node.children().iter().all(|child| child.children().is_empty() || child.name().is_empty())
+    }
+
+    fn into_elements(self) -> Vec<Value> {
+        match self {
+            Value::Token(token) => vec![Value::Token(token)],
+            Value::Keyed(nodes) => {
+                if nodes.len() > 1 {
+                    // Repeated keys, one element per key
+                    return nodes.into_iter().map(|node| Value::Keyed(vec![node])).collect();
+                }
+                let Some(node) = nodes.into_iter().next() else {
+                    return Vec::new();
+                };
+                if !Value::is_list(&node) {
+                    return vec![Value::Keyed(vec![node])];
+                }
+                node.children().into_iter().map(|child| {
+                    if child.children().is_empty() {
+                        Value::Token(child.name())
+                    } else {
+                        // Anonymous block, its content is the element
+                        Value::Keyed(vec![child])
+                    }
+                }).collect()
+            }
+        }
+    }
+
+    // Children grouped by key, in the order of their first appearance
+    // A child without a value is an empty block, e.g. workforce_scaled = { }
+    fn into_entries(self) -> Vec<(String, Vec<Node>)> {
+        let Some(node) = self.node() else {
+            return Vec::new();
+        };
+        let mut entries: Vec<(String, Vec<Node>)> = Vec::new();
+        for child in node.children() {
+            match entries.iter_mut().find(|(key, _)| *key == child.name()) {
+                Some((_, nodes)) => nodes.push(child),
+                None => entries.push((child.name(), vec![child])),
+            }
+        }
+        entries
+    }
+
+    fn parse<T: std::str::FromStr>(&self, expected: &str) -> Result<T, Error> {
+        let scalar = self.expect_scalar()?;
+        scalar.parse::<T>().map_err(|_| Error(format!("Expected {}, found {} in {}", expected, scalar, self.name())))
+    }
+}
+
+fn unquote(value: &str) -> String {
+    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(inner) => inner.replace("\\\"", "\""),
+        None => value.to_string(),
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "yes" | "true" => Some(true),
+        "no" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+macro_rules! deserialize_number {
+    ($($method:ident => $visit:ident: $ty:ty),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visitor.$visit(self.parse::<$ty>(stringify!($ty))?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.scalar() {
+            Some(scalar) => {
+                if let Some(b) = parse_bool(&scalar) {
+                    visitor.visit_bool(b)
+                } else if let Ok(i) = scalar.parse::<i64>() {
+                    visitor.visit_i64(i)
+                } else if let Ok(f) = scalar.parse::<f64>() {
+                    visitor.visit_f64(f)
+                } else {
+                    visitor.visit_string(unquote(&scalar))
+                }
+            }
+            None => match self.node() {
+                Some(node) if Value::is_list(node) && !node.children().is_empty() => self.deserialize_seq(visitor),
+                _ => self.deserialize_map(visitor),
+            },
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let scalar = self.expect_scalar()?;
+        match parse_bool(&scalar) {
+            Some(b) => visitor.visit_bool(b),
+            None => Err(Error(format!("Expected yes or no, found {} in {}", scalar, self.name()))),
+        }
+    }
+
+    deserialize_number! {
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+        deserialize_char => visit_char: char
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.expect_scalar()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_byte_buf(self.expect_scalar()?.into_bytes())
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // Missing keys never get here, serde already treats them as None
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(Elements { elements: self.into_elements().into_iter() })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(Entries { entries: self.into_entries().into_iter(), value: None })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        // Only unit variants, written as a plain value
+        visitor.visit_enum(self.expect_scalar()?.into_deserializer())
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+}
+
+struct Elements {
+    elements: std::vec::IntoIter<Value>,
+}
+
+impl<'de> SeqAccess<'de> for Elements {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        match self.elements.next() {
+            Some(value) => seed.deserialize(value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.elements.len())
+    }
+}
+
+struct Entries {
+    entries: std::vec::IntoIter<(String, Vec<Node>)>,
+    value: Option<Vec<Node>>,
+}
+
+impl<'de> MapAccess<'de> for Entries {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        match self.entries.next() {
+            Some((key, nodes)) => {
+                self.value = Some(nodes);
+                seed.deserialize(Value::Token(key)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let nodes = self.value.take().ok_or_else(|| Error("Value without a key".to_string()))?;
+        seed.deserialize(Value::Keyed(nodes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::Parser;
+
+    fn read<T: DeserializeOwned>(text: &str) -> Result<T, String> {
+        from_tree(&Parser::new().parse(text.to_string()))
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Pm {
+        #[serde(default)]
+        texture: Option<String>,
+        #[serde(default)]
+        unlocking_technologies: Vec<String>,
+        #[serde(default)]
+        is_default: bool,
+        #[serde(default = "one")]
+        weight: f32,
+    }
+
+    fn one() -> f32 {
+        1.
+    }
+
+    #[test]
+    fn repeated_keys_into_vec() {
+        #[derive(Deserialize)]
+        struct Need {
+            entry: Vec<Entry>,
+        }
+        #[derive(Deserialize)]
+        struct Entry {
+            goods: String,
+        }
+        let need: Need = read("entry = { goods = grain } entry = { goods = fish } entry = { goods = meat }").unwrap();
+        assert_eq!(need.entry.iter().map(|e| e.goods.as_str()).collect::<Vec<&str>>(), ["grain", "fish", "meat"]);
+
+        // A single key is still a Vec of one
+        let need: Need = read("entry = { goods = grain }").unwrap();
+        assert_eq!(need.entry.len(), 1);
+    }
+
+    #[test]
+    fn lists() {
+        let pms: HashMap<String, Pm> = read("pm_a = { unlocking_technologies = { tech_a tech_b } } pm_b = { unlocking_technologies = { tech_c } }").unwrap();
+        assert_eq!(pms["pm_a"].unlocking_technologies, ["tech_a", "tech_b"]);
+        assert_eq!(pms["pm_b"].unlocking_technologies, ["tech_c"]);
+    }
+
+    #[test]
+    fn yes_no_into_bool() {
+        let pms: HashMap<String, Pm> = read("pm_a = { is_default = yes } pm_b = { is_default = no } pm_c = { is_default = true }").unwrap();
+        assert!(pms["pm_a"].is_default);
+        assert!(!pms["pm_b"].is_default);
+        assert!(pms["pm_c"].is_default);
+        assert!(read::<HashMap<String, Pm>>("pm_a = { is_default = maybe }").is_err());
+    }
+
+    #[test]
+    fn unknown_fields_ignored() {
+        let pms: HashMap<String, Pm> = read("pm_a = { is_default = yes building_modifiers = { workforce_scaled = { building_input_coal_add = 5 } } ai_value = 10 }").unwrap();
+        assert!(pms["pm_a"].is_default);
+    }
+
+    #[test]
+    fn quoted_values_unquoted() {
+        let pms: HashMap<String, Pm> = read(r#"pm_a = { texture = "gfx/a.dds" } pm_b = { texture = "say \"hi\"" } pm_c = { texture = bare }"#).unwrap();
+        assert_eq!(pms["pm_a"].texture.as_deref(), Some("gfx/a.dds"));
+        assert_eq!(pms["pm_b"].texture.as_deref(), Some("say \"hi\""));
+        assert_eq!(pms["pm_c"].texture.as_deref(), Some("bare"));
+    }
+
+    #[test]
+    fn options_and_defaults() {
+        let pms: HashMap<String, Pm> = read("pm_a = { } pm_b = { texture = a weight = 2.5 }").unwrap();
+        assert_eq!(pms["pm_a"], Pm { texture: None, unlocking_technologies: Vec::new(), is_default: false, weight: 1. });
+        assert_eq!(pms["pm_b"], Pm { texture: Some("a".to_string()), unlocking_technologies: Vec::new(), is_default: false, weight: 2.5 });
+
+        #[derive(Deserialize)]
+        struct Required {
+            cost: i32,
+        }
+        assert_eq!(read::<HashMap<String, Required>>("good = { cost = 20 }").unwrap()["good"].cost, 20);
+        assert!(read::<HashMap<String, Required>>("good = { }").is_err());
+        assert!(read::<HashMap<String, Required>>("good = { cost = many }").is_err());
+    }
+}
@@ -0,0 +1,286 @@
+use std::fmt::{self, Display};
+
+use crate::{node::Node, Tree};
+
+// A single difference between two trees
+// The path is the list of keys from the root, e.g. ["pm_steel", "building_modifiers", "workforce_scaled"]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added { path: Vec<String>, value: String },
+    Removed { path: Vec<String>, value: String },
+    Changed { path: Vec<String>, old: String, new: String },
+}
+
+impl Change {
+    pub fn get_path(&self) -> &Vec<String> {
+        match self {
+            Change::Added { path, .. } | Change::Removed { path, .. } | Change::Changed { path, .. } => path,
+        }
+    }
+
+    pub fn path_string(&self) -> String {
+        self.get_path().join(".")
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Change::Added { .. } => "added",
+            Change::Removed { .. } => "removed",
+            Change::Changed { .. } => "changed",
+        }
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { value, .. } => write!(f, "+ {} = {}", self.path_string(), value),
+            Change::Removed { value, .. } => write!(f, "- {} = {}", self.path_string(), value),
+            Change::Changed { old, new, .. } => write!(f, "~ {}: {} -> {}", self.path_string(), old, new),
+        }
+    }
+}
+
+// Semantic differences between two trees
+// Keyed blocks are compared key by key so moving a key around is not a change,
+// lists (e.g. production_methods = { pm_a pm_b }) are compared in order
+// A key defined more than once (e.g. activate_law) is compared as a set of values
+#[derive(Debug, Clone, Default)]
+pub struct Diff {
+    changes: Vec<Change>,
+}
+
+impl Diff {
+    // The names of the roots are not compared, only what is inside of them
+    pub fn new(old: &Tree, new: &Tree) -> Diff {
+        let mut diff = Diff::default();
+        diff.compare_blocks(&mut Vec::new(), old.root(), new.root());
+        diff
+    }
+
+    pub fn get_changes(&self) -> &Vec<Change> {
+        &self.changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    // One line per change: + added, - removed, ~ changed
+    pub fn to_text(&self) -> String {
+        let mut result = String::new();
+        for change in &self.changes {
+            result.push_str(&format!("{}\n", change));
+        }
+        result
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let changes: Vec<serde_json::Value> = self.changes.iter().map(|change| match change {
+            Change::Added { value, .. } | Change::Removed { value, .. } => serde_json::json!({
+                "change": change.kind(),
+                "path": change.get_path(),
+                "value": value
+            }),
+            Change::Changed { old, new, .. } => serde_json::json!({
+                "change": change.kind(),
+                "path": change.get_path(),
+                "old": old,
+                "new": new
+            }),
+        }).collect();
+        serde_json::Value::Array(changes)
+    }
+
+    fn compare_blocks(&mut self, path: &mut Vec<String>, old: &Node, new: &Node) {
+        if is_list(old) && is_list(new) {
+            // Order matters in a list, any difference changes the whole list
+            let (old_value, new_value) = (render(old), render(new));
+            if old_value != new_value {
+                self.changes.push(Change::Changed { path: path.clone(), old: old_value, new: new_value });
+            }
+            return;
+        }
+
+        let old_entries = entries(old);
+        let new_entries = entries(new);
+        for (key, old_nodes) in &old_entries {
+            path.push(key.clone());
+            match new_entries.iter().find(|(k, _)| k == key) {
+                Some((_, new_nodes)) => self.compare_keyed(path, old_nodes, new_nodes),
+                None => {
+                    for node in old_nodes {
+                        self.changes.push(Change::Removed { path: path.clone(), value: render(node) });
+                    }
+                }
+            }
+            path.pop();
+        }
+        for (key, new_nodes) in &new_entries {
+            if old_entries.iter().any(|(k, _)| k == key) {
+                continue;
+            }
+            path.push(key.clone());
+            for node in new_nodes {
+                self.changes.push(Change::Added { path: path.clone(), value: render(node) });
+            }
+            path.pop();
+        }
+    }
+
+    // Every node with the same key on both sides
+    fn compare_keyed(&mut self, path: &mut Vec<String>, old: &[Node], new: &[Node]) {
+        if let ([old], [new]) = (old, new) {
+            self.compare_nodes(path, old, new);
+            return;
+        }
+        // Repeated key, the values that are on both sides are not changes whatever their order
+        let mut new_values: Vec<String> = new.iter().map(render).collect();
+        for node in old {
+            let value = render(node);
+            match new_values.iter().position(|v| *v == value) {
+                Some(index) => {
+                    new_values.remove(index);
+                }
+                None => self.changes.push(Change::Removed { path: path.clone(), value }),
+            }
+        }
+        for value in new_values {
+            self.changes.push(Change::Added { path: path.clone(), value });
+        }
+    }
+
+    fn compare_nodes(&mut self, path: &mut Vec<String>, old: &Node, new: &Node) {
+        match (scalar(old), scalar(new)) {
+            (Some(old_value), Some(new_value)) => {
+                if old_value != new_value {
+                    self.changes.push(Change::Changed { path: path.clone(), old: old_value, new: new_value });
+                }
+            }
+            (None, None) => self.compare_blocks(path, old, new),
+            // A value replaced by a block or the other way around
+            _ => self.changes.push(Change::Changed { path: path.clone(), old: render(old), new: render(new) }),
+        }
+    }
+}
+
+impl Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_text())
+    }
+}
+
+// Shortcut for Diff::new
+pub fn diff(old: &Tree, new: &Tree) -> Diff {
+    Diff::new(old, new)
+}
+
+// The value of key = value
+fn scalar(node: &Node) -> Option<String> {
+    let children = node.children();
+    match children.as_slice() {
+        [child] if child.children().is_empty() && !child.name().is_empty() => Some(child.name()),
+        _ => None,
+    }
+}
+
+// A block of bare tokens and anonymous blocks, an empty block is not a list
+fn is_list(node: &Node) -> bool {
+    let children = node.children();
+    !children.is_empty() && children.iter().all(|child| child.name().is_empty() || child.children().is_empty())
+}
+
+// Children grouped by key, in the order of their first appearance
+fn entries(node: &Node) -> Vec<(String, Vec<Node>)> {
+    let mut entries: Vec<(String, Vec<Node>)> = Vec::new();
+    for child in node.children() {
+        match entries.iter_mut().find(|(key, _)| *key == child.name()) {
+            Some((_, nodes)) => nodes.push(child),
+            None => entries.push((child.name(), vec![child])),
+        }
+    }
+    entries
+}
+
+// The value of a node on a single line, blocks are written as { key = value ... }
+fn render(node: &Node) -> String {
+    if let Some(value) = scalar(node) {
+        return value;
+    }
+    let children = node.children();
+    if children.is_empty() {
+        return "{ }".to_string();
+    }
+    let content: Vec<String> = children.iter().map(|child| {
+        if child.children().is_empty() {
+            child.name()
+        } else if child.name().is_empty() {
+            render(child)
+        } else {
+            format!("{} = {}", child.name(), render(child))
+        }
+    }).collect();
+    format!("{{ {} }}", content.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Parser;
+
+    use super::*;
+
+    fn parse_diff(old: &str, new: &str) -> Diff {
+        diff(&Parser::new().parse(old.to_string()), &Parser::new().parse(new.to_string()))
+    }
+
+    fn path(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|k| k.to_string()).collect()
+    }
+
+    #[test]
+    fn keyed_blocks_ignore_order() {
+        let old = "pm = { texture = a building_modifiers = { workforce_scaled = { input = 1 output = 2 } } }";
+        let new = "pm = { building_modifiers = { workforce_scaled = { output = 2 input = 1 } } texture = a }";
+        assert!(parse_diff(old, new).is_empty());
+
+        // Repeated keys are a set of values
+        assert!(parse_diff("a = { law = x law = y }", "a = { law = y law = x }").is_empty());
+    }
+
+    #[test]
+    fn lists_keep_order() {
+        let changes = parse_diff("pmg = { pms = { pm_a pm_b } }", "pmg = { pms = { pm_b pm_a } }");
+        assert_eq!(changes.get_changes(), &vec![Change::Changed {
+            path: path(&["pmg", "pms"]),
+            old: "{ pm_a pm_b }".to_string(),
+            new: "{ pm_b pm_a }".to_string(),
+        }]);
+    }
+
+    #[test]
+    fn added_removed_changed() {
+        let changes = parse_diff("pm = { a = 1 b = 2 law = x }", "pm = { a = 3 c = { d = 4 } law = y }");
+        assert_eq!(changes.get_changes(), &vec![
+            Change::Changed { path: path(&["pm", "a"]), old: "1".to_string(), new: "3".to_string() },
+            Change::Removed { path: path(&["pm", "b"]), value: "2".to_string() },
+            Change::Changed { path: path(&["pm", "law"]), old: "x".to_string(), new: "y".to_string() },
+            Change::Added { path: path(&["pm", "c"]), value: "{ d = 4 }".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn text_and_json() {
+        let changes = parse_diff("pm = { a = 1 b = 2 }", "pm = { a = 3 c = { d = 4 e = 5 } }");
+        assert_eq!(changes.to_text(), "~ pm.a: 1 -> 3\n- pm.b = 2\n+ pm.c = { d = 4 e = 5 }\n");
+        assert_eq!(changes.to_string(), changes.to_text());
+        assert_eq!(changes.to_json(), serde_json::json!([
+            { "change": "changed", "path": ["pm", "a"], "old": "1", "new": "3" },
+            { "change": "removed", "path": ["pm", "b"], "value": "2" },
+            { "change": "added", "path": ["pm", "c"], "value": "{ d = 4 e = 5 }" },
+        ]));
+    }
+}
@@ -1,19 +1,38 @@
 mod node;
 mod tree;
 pub mod utils;
+pub mod localization;
+pub mod stream;
+pub mod save;
+pub mod de;
+pub mod ser;
+pub mod diff;
+pub mod technology;
+
+use std::io::Read;
 
 use node::Node;
-pub use tree::Tree;
+pub use tree::{NodeId, Tree};
+pub use stream::{Event, EventReader};
 
+// Builds a Tree out of parser events
+// Use EventReader directly to only extract parts of a file
 pub struct Parser {
     root: Node,
+    // The blocks that are currently open, the root is not included
+    stack: Vec<Node>,
     unique_id: usize,
 }
 
 impl Parser {
     pub fn new() -> Parser {
+        Parser::with_root("root")
+    }
+
+    pub fn with_root(name: &str) -> Parser {
         Parser {
-            root: Node::new("root".to_string(), 0),
+            root: Node::new(name.to_string(), 0),
+            stack: Vec::new(),
             unique_id: 0
         }
     }
@@ -22,92 +41,51 @@ impl Parser {
         self.unique_id += 1;
         Node::new(name, self.unique_id)
     }
-    /// A horror beyond human comprehension
-    /// Lol It's so long I'm sorry
-    /// Surely there's a recursive way to do this
-    /// But you'd need to pass around a ton of references
-    /// And it's just not pretty
-    /// I'm not sure if this is pretty either to be fair
-    pub fn parse(mut self, text: String) -> Tree {
-        let mut current = self.root.clone();
-        let mut leaf_list = Vec::new();
-        let mut equal_seen = false;
-        let mut line_skip = false;
-        let mut last_token = String::new();
 
-        for line in text.lines() {
-            // Add a space before the comment
-            // This fixes an issue where a comment is not recognized
-            // if it's part of a token
-            let fixed_line = line.replace("#", " #").replace("\u{feff}", ""); 
-            for token in fixed_line.split_whitespace() {
-                if line_skip {
-                    break;
-                }
-                match token {
-                    "=" | "?=" => {
-                        equal_seen = true;
-                        leaf_list = Vec::new();
-                    }
-                    "{" => {
-
-                        leaf_list = Vec::new();
-                        if equal_seen {
-                            let key = last_token.clone();
-                            let new_node = self.new_node(key);
-                            current.add_child(new_node.clone());
-                            current = new_node;
-                        }
-                        equal_seen = false;
-                    }
-                    "}" => {
-                        // Go back to the parent node
-                        if leaf_list.len() > 0 {
-                            for leaf in leaf_list {
-                                let leaf_node = self.new_node(leaf);
-                                current.add_child(leaf_node);
-                            }
-                            leaf_list = Vec::new();
-                        }
-                        let parent_node = self.find_parent(&self.root, &current).unwrap();
-                        current = parent_node;
-                    }
-                    "#" => {
-                        // This is a comment
-                        // We can ignore this line
-                        line_skip = true;
-                    }
-                    _ => {
-                        if equal_seen {
-                            let key = last_token.clone();
-                            let value = token.to_string();
-                            let mut new_node = self.new_node(key);
-                            let leaf = self.new_node(value);   
-                            new_node.add_child(leaf);
-                            current.add_child(new_node.clone());
-                        } else {
-                            leaf_list.push(token.to_string());
-                        }
-                        equal_seen = false;
-                        last_token = token.to_string();
-                    }
-                }
-            }
-            line_skip = false;
-        }
+    pub fn parse(self, text: String) -> Tree {
+        // Reading from a string can't fail
+        self.parse_reader(text.as_bytes()).unwrap()
+    }
 
-        Tree::new(self.root, self.unique_id+1)
+    // Builds the whole tree out of any reader, e.g. a file
+    pub fn parse_reader(self, reader: impl Read) -> Result<Tree, String> {
+        self.parse_events(EventReader::new(reader))
     }
 
-    fn find_parent(&self, root: &Node, node: &Node) -> Option<Node> {
-        if root.children().contains(node) {
-            return Some(root.clone());
+    pub fn parse_events(mut self, events: impl Iterator<Item = Result<Event, String>>) -> Result<Tree, String> {
+        for event in events {
+            self.push(event?);
         }
-        for child in &root.children() {
-            if let Some(parent) = self.find_parent(child, node) {
-                return Some(parent);
+        Ok(self.finish())
+    }
+
+    // Adds a single event to the tree being built
+    pub fn push(&mut self, event: Event) {
+        let mut current = self.stack.last().unwrap_or(&self.root).clone();
+        match event {
+            Event::BeginBlock(key) => {
+                let new_node = self.new_node(key);
+                current.add_child(new_node.clone());
+                self.stack.push(new_node);
+            }
+            Event::KeyValue(key, value) => {
+                let mut new_node = self.new_node(key);
+                let leaf = self.new_node(value);
+                new_node.add_child(leaf);
+                current.add_child(new_node);
+            }
+            Event::Token(token) => {
+                let leaf = self.new_node(token);
+                current.add_child(leaf);
+            }
+            Event::EndBlock => {
+                // Go back to the parent node
+                self.stack.pop();
             }
         }
-        None
     }
-}
\ No newline at end of file
+
+    pub fn finish(self) -> Tree {
+        Tree::new(self.root, self.unique_id+1)
+    }
+}
@@ -0,0 +1,342 @@
+use std::{collections::HashMap, fs, path::{Path, PathBuf}};
+
+// Every language the game ships localization for
+pub const LANGUAGES: [&str; 11] = [
+    "english",
+    "braz_por",
+    "french",
+    "german",
+    "japanese",
+    "korean",
+    "polish",
+    "russian",
+    "simp_chinese",
+    "spanish",
+    "turkish",
+];
+
+pub const DEFAULT_LANGUAGE: &str = "english";
+
+// Holds every localization key of a single language
+// Loaded from the localization/<language> folder of the game and the mod
+#[derive(Debug, Clone)]
+pub struct Localization {
+    language: String,
+    entries: HashMap<String, String>,
+}
+
+impl Localization {
+    pub fn new(language: impl Into<String>) -> Localization {
+        Localization {
+            language: language.into(),
+            entries: HashMap::new(),
+        }
+    }
+
+    // Loads the game files first and then the mod files
+    // so that the mod can overwrite any key of the game
+    pub fn load(mod_path: &str, game_path: &str, language: &str) -> Localization {
+        let mut localization = Localization::new(language);
+        for path in localization_files(mod_path, game_path, language) {
+            if let Ok(text) = fs::read_to_string(&path) {
+                localization.parse_text(&text);
+            }
+        }
+        localization
+    }
+
+    pub fn from_text(language: impl Into<String>, text: &str) -> Localization {
+        let mut localization = Localization::new(language);
+        localization.parse_text(text);
+        localization
+    }
+
+    // The files look like this:
+    // l_english:
+    //  key:0 "Value"
+    //  other_key: "Other value" # comment
+    fn parse_text(&mut self, text: &str) {
+        for line in text.lines() {
+            let line = line.replace('\u{feff}', "");
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, rest)) = line.split_once(':') else {
+                continue;
+            };
+            // The value is everything between the first and the last quote
+            // Quotes inside the value are not escaped in the game files
+            let (Some(start), Some(end)) = (rest.find('"'), rest.rfind('"')) else {
+                continue;
+            };
+            if start == end {
+                continue;
+            }
+            self.entries.insert(key.trim().to_string(), rest[start + 1..end].to_string());
+        }
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    pub fn get(&self, key: &str) -> Option<&String> {
+        self.entries.get(key)
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    // Returns the localized name or the key itself if there is none
+    pub fn localize(&self, key: &str) -> String {
+        self.get(key).cloned().unwrap_or_else(|| key.to_string())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+// Loads every language that is available in the mod or the game
+pub fn load_all(mod_path: &str, game_path: &str) -> Vec<Localization> {
+    available_languages(mod_path, game_path)
+        .iter()
+        .map(|language| Localization::load(mod_path, game_path, language))
+        .collect()
+}
+
+// Languages that have a localization folder in either the mod or the game
+pub fn available_languages(mod_path: &str, game_path: &str) -> Vec<String> {
+    LANGUAGES
+        .iter()
+        .filter(|language| {
+            language_folder(mod_path, language).is_dir() || language_folder(game_path, language).is_dir()
+        })
+        .map(|language| language.to_string())
+        .collect()
+}
+
+fn language_folder(root: &str, language: &str) -> PathBuf {
+    Path::new(root).join("localization").join(language)
+}
+
+// Collects every file of the language in the order they are loaded: the game files, then the mod files
+// so the mod overwrites the game whatever the file names, and the files of a "replace" folder last
+// A mod file replaces the game file with the same relative path
+fn localization_files(mod_path: &str, game_path: &str, language: &str) -> Vec<PathBuf> {
+    let suffix = format!("_l_{}.yml", language);
+    // relative path -> (from the mod, path)
+    let mut files: HashMap<PathBuf, (bool, PathBuf)> = HashMap::new();
+    for (root, from_mod) in [(game_path, false), (mod_path, true)] {
+        let folder = language_folder(root, language);
+        let mut found = Vec::new();
+        collect_files(&folder, &mut found);
+        for path in found {
+            let is_localization = path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(&suffix));
+            if let (true, Ok(relative)) = (is_localization, path.strip_prefix(&folder)) {
+                files.insert(relative.to_path_buf(), (from_mod, path.clone()));
+            }
+        }
+    }
+
+    let mut files: Vec<(PathBuf, (bool, PathBuf))> = files.into_iter().collect();
+    files.sort_by_key(|(relative, (from_mod, _))| (relative.components().any(|c| c.as_os_str() == "replace"), *from_mod, relative.clone()));
+    files.into_iter().map(|(_, (_, path))| path).collect()
+}
+
+fn collect_files(folder: &Path, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, found);
+        } else {
+            found.push(path);
+        }
+    }
+}
+
+// A key that is not localized in at least one of the checked languages
+#[derive(Debug, Clone)]
+pub struct MissingKey {
+    category: String,
+    key: String,
+    missing_in: Vec<String>,
+}
+
+impl MissingKey {
+    pub fn get_category(&self) -> &str {
+        &self.category
+    }
+
+    pub fn get_key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn get_missing_in(&self) -> &Vec<String> {
+        &self.missing_in
+    }
+}
+
+// Lists the keys that lack a localization entry
+// Either in every language or only in some of them
+#[derive(Debug, Clone)]
+pub struct MissingKeyReport {
+    languages: Vec<String>,
+    missing: Vec<MissingKey>,
+}
+
+impl MissingKeyReport {
+    // keys are (category, key) pairs, e.g. ("building", "building_food_industry")
+    pub fn new(keys: &[(String, String)], localizations: &[Localization]) -> MissingKeyReport {
+        let languages = localizations.iter().map(|l| l.language().to_string()).collect();
+        let mut missing: Vec<MissingKey> = keys.iter().filter_map(|(category, key)| {
+            let missing_in: Vec<String> = localizations.iter()
+                .filter(|localization| !localization.contains(key))
+                .map(|localization| localization.language().to_string())
+                .collect();
+            if missing_in.is_empty() {
+                return None;
+            }
+            Some(MissingKey { category: category.clone(), key: key.clone(), missing_in })
+        }).collect();
+        missing.sort_by(|a, b| (&a.category, &a.key).cmp(&(&b.category, &b.key)));
+        missing.dedup_by(|a, b| a.category == b.category && a.key == b.key);
+        MissingKeyReport { languages, missing }
+    }
+
+    // Keys that are not localized in any of the languages
+    pub fn missing_everywhere(&self) -> Vec<&MissingKey> {
+        self.missing.iter().filter(|m| m.missing_in.len() == self.languages.len()).collect()
+    }
+
+    // Keys that are localized in some languages but not in others
+    pub fn missing_partially(&self) -> Vec<&MissingKey> {
+        self.missing.iter().filter(|m| m.missing_in.len() < self.languages.len()).collect()
+    }
+
+    pub fn missing_in(&self, language: &str) -> Vec<&MissingKey> {
+        self.missing.iter().filter(|m| m.missing_in.iter().any(|l| l == language)).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut result = format!("Missing localization report ({})\n", self.languages.join(", "));
+        for language in &self.languages {
+            let missing = self.missing_in(language);
+            result.push_str(&format!("\n== {}: {} missing\n", language, missing.len()));
+            for m in missing {
+                result.push_str(&format!("  {} {}\n", m.category, m.key));
+            }
+        }
+        let partial = self.missing_partially();
+        result.push_str(&format!("\n== Only localized in some languages: {}\n", partial.len()));
+        for m in partial {
+            result.push_str(&format!("  {} {} (missing in {})\n", m.category, m.key, m.missing_in.join(", ")));
+        }
+        result
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let entry = |m: &MissingKey| serde_json::json!({
+            "category": m.category,
+            "key": m.key,
+            "missing_in": m.missing_in
+        });
+        let by_language: serde_json::Map<String, serde_json::Value> = self.languages.iter()
+            .map(|language| (language.clone(), self.missing_in(language).into_iter().map(entry).collect()))
+            .collect();
+        serde_json::json!({
+            "languages": self.languages,
+            "missing": by_language,
+            "missing_everywhere": self.missing_everywhere().into_iter().map(entry).collect::<Vec<_>>(),
+            "missing_partially": self.missing_partially().into_iter().map(entry).collect::<Vec<_>>()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, relative: &str, text: &str) {
+        let path = root.join("localization").join("english").join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+
+    #[test]
+    fn mod_overwrites_game_whatever_the_file_names() {
+        let root = std::env::temp_dir().join(format!("vic3_localization_{}", std::process::id()));
+        let (game, mod_root) = (root.join("game"), root.join("mod"));
+        write(&game, "zz_game_l_english.yml", "l_english:\n key: \"game\"\n only_game: \"game\"\n");
+        write(&game, "same_l_english.yml", "l_english:\n same: \"game\"\n");
+        write(&mod_root, "aa_mod_l_english.yml", "l_english:\n key: \"mod\"\n");
+        write(&mod_root, "same_l_english.yml", "l_english:\n other: \"mod\"\n");
+        write(&game, "replace/a_l_english.yml", "l_english:\n only_game: \"replaced\"\n");
+
+        let localization = Localization::load(mod_root.to_str().unwrap(), game.to_str().unwrap(), "english");
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(localization.localize("key"), "mod");
+        assert_eq!(localization.localize("only_game"), "replaced");
+        // The mod file with the same relative path replaces the whole game file
+        assert!(!localization.contains("same"));
+        assert_eq!(localization.localize("other"), "mod");
+    }
+
+    #[test]
+    fn missing_key_report() {
+        let localizations = [
+            Localization::from_text("english", "l_english:\n building_a: \"A\"\n pm_b: \"B\"\n"),
+            Localization::from_text("french", "l_french:\n building_a: \"A\"\n"),
+        ];
+        let keys = [
+            ("building".to_string(), "building_a".to_string()),
+            ("pm".to_string(), "pm_b".to_string()),
+            ("pm".to_string(), "pm_c".to_string()),
+            // Listed twice, e.g. a PM of two buildings
+            ("pm".to_string(), "pm_c".to_string()),
+        ];
+        let report = MissingKeyReport::new(&keys, &localizations);
+        assert!(!report.is_empty());
+        let keys_of = |missing: Vec<&MissingKey>| missing.iter().map(|m| m.get_key().to_string()).collect::<Vec<String>>();
+        assert_eq!(keys_of(report.missing_in("english")), ["pm_c"]);
+        assert_eq!(keys_of(report.missing_in("french")), ["pm_b", "pm_c"]);
+        assert_eq!(keys_of(report.missing_everywhere()), ["pm_c"]);
+        assert_eq!(keys_of(report.missing_partially()), ["pm_b"]);
+
+        assert_eq!(report.to_text(), "Missing localization report (english, french)\n\
+            \n== english: 1 missing\n  pm pm_c\n\
+            \n== french: 2 missing\n  pm pm_b\n  pm pm_c\n\
+            \n== Only localized in some languages: 1\n  pm pm_b (missing in french)\n");
+
+        let pm_b = serde_json::json!({ "category": "pm", "key": "pm_b", "missing_in": ["french"] });
+        let pm_c = serde_json::json!({ "category": "pm", "key": "pm_c", "missing_in": ["english", "french"] });
+        assert_eq!(report.to_json(), serde_json::json!({
+            "languages": ["english", "french"],
+            "missing": { "english": [pm_c], "french": [pm_b, pm_c] },
+            "missing_everywhere": [pm_c],
+            "missing_partially": [pm_b]
+        }));
+
+        assert!(MissingKeyReport::new(&keys[..1], &localizations).is_empty());
+    }
+}
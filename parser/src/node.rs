@@ -60,6 +60,61 @@ impl Node {
     pub fn set_unique_id(&mut self, unique_id: usize) {
         self.node.borrow_mut().set_unique_id(unique_id);
     }
+
+    pub fn unique_id(&self) -> usize {
+        self.node.borrow().unique_id
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.node.borrow_mut().name = name;
+    }
+
+    pub fn insert_child(&mut self, index: usize, child: Node) {
+        self.node.borrow_mut().children.insert(index, child.node);
+    }
+
+    pub fn remove_child(&mut self, index: usize) -> Node {
+        Node { node: self.node.borrow_mut().children.remove(index) }
+    }
+
+    // Unlike clone, this copies the node and all of its children so nothing is shared with the original
+    // The copy keeps the same unique ids
+    pub fn deep_clone(&self) -> Node {
+        let raw = self.node.borrow();
+        let mut copy = Node::new(raw.name.clone(), raw.unique_id);
+        for child in &raw.children {
+            copy.add_child(Node { node: child.clone() }.deep_clone());
+        }
+        copy
+    }
+
+    // Same as deep_clone but every node of the copy gets a new unique id, starting at next_id
+    pub fn deep_clone_with_ids(&self, next_id: &mut usize) -> Node {
+        let raw = self.node.borrow();
+        let mut copy = Node::new(raw.name.clone(), *next_id);
+        *next_id += 1;
+        for child in &raw.children {
+            copy.add_child(Node { node: child.clone() }.deep_clone_with_ids(next_id));
+        }
+        copy
+    }
+
+    // The node with this unique id, searching this node and everything below it
+    pub fn find(&self, unique_id: usize) -> Option<Node> {
+        if self.unique_id() == unique_id {
+            return Some(self.clone());
+        }
+        self.children().iter().find_map(|child| child.find(unique_id))
+    }
+
+    // The parent of the node with this unique id and the position of the node in it
+    pub fn find_parent(&self, unique_id: usize) -> Option<(Node, usize)> {
+        let children = self.children();
+        if let Some(index) = children.iter().position(|child| child.unique_id() == unique_id) {
+            return Some((self.clone(), index));
+        }
+        children.iter().find_map(|child| child.find_parent(unique_id))
+    }
 }
 
 impl PartialEq for RawNode {
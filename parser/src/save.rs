@@ -0,0 +1,278 @@
+use std::{fs::File, io::{BufRead, BufReader, Read, Seek, SeekFrom}, path::{Path, PathBuf}};
+
+use crate::{Event, EventReader, Parser, Tree};
+
+// What comes after the header line, given by the two hex digits after the version
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SaveKind {
+    Text,
+    Binary,
+    // Metadata in text followed by a zip with the gamestate
+    UnifiedText,
+    UnifiedBinary,
+    SplitText,
+    SplitBinary,
+    Unknown(u8),
+}
+
+impl SaveKind {
+    fn from_code(code: u8) -> SaveKind {
+        match code {
+            0 => SaveKind::Text,
+            1 => SaveKind::Binary,
+            2 => SaveKind::UnifiedText,
+            3 => SaveKind::UnifiedBinary,
+            4 => SaveKind::SplitText,
+            5 => SaveKind::SplitBinary,
+            code => SaveKind::Unknown(code),
+        }
+    }
+
+    pub fn is_binary(&self) -> bool {
+        matches!(self, SaveKind::Binary | SaveKind::UnifiedBinary | SaveKind::SplitBinary)
+    }
+}
+
+// The first line of a save file, e.g. SAV0102b4a0e6c10004c1a000000000
+// SAV, the version, the kind, a random id and the size of the metadata section
+#[derive(Debug, Clone)]
+pub struct SaveHeader {
+    version: u8,
+    kind: SaveKind,
+    metadata_size: u64,
+    // Length of the header line including the line break
+    length: u64,
+}
+
+impl SaveHeader {
+    pub fn parse(line: &str) -> Result<SaveHeader, String> {
+        let trimmed = line.trim_end_matches(['\r', '\n']);
+        if !trimmed.starts_with("SAV") || trimmed.len() < 7 {
+            return Err("Not a save file: missing SAV header".to_string());
+        }
+        let hex = |range: std::ops::Range<usize>| {
+            trimmed.get(range).and_then(|s| u64::from_str_radix(s, 16).ok())
+        };
+        let version = hex(3..5).ok_or("Invalid save version")? as u8;
+        let kind = SaveKind::from_code(hex(5..7).ok_or("Invalid save kind")? as u8);
+        let metadata_size = hex(15..23).unwrap_or(0);
+        Ok(SaveHeader { version, kind, metadata_size, length: line.len() as u64 })
+    }
+
+    pub fn get_version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn get_kind(&self) -> SaveKind {
+        self.kind
+    }
+
+    pub fn get_metadata_size(&self) -> u64 {
+        self.metadata_size
+    }
+}
+
+// A plaintext Victoria 3 save
+// The gamestate is never loaded in memory as a whole, it's read through the streaming parser
+pub struct SaveFile {
+    path: PathBuf,
+    header: SaveHeader,
+}
+
+const ZIP_LOCAL_FILE: u32 = 0x04034b50;
+const GAMESTATE_ENTRY: &str = "gamestate";
+
+impl SaveFile {
+    pub fn open(path: impl AsRef<Path>) -> Result<SaveFile, String> {
+        let path = path.as_ref().to_path_buf();
+        let mut reader = BufReader::new(File::open(&path).map_err(|e| format!("{}: {}", path.display(), e))?);
+        let mut line = String::new();
+        reader.read_line(&mut line).map_err(|e| e.to_string())?;
+        let header = SaveHeader::parse(&line)?;
+        if header.kind.is_binary() {
+            return Err("Binary saves are not supported, save the game in debug mode to get a plaintext save".to_string());
+        }
+        Ok(SaveFile { path, header })
+    }
+
+    pub fn get_header(&self) -> &SaveHeader {
+        &self.header
+    }
+
+    // The raw text of the gamestate, either right after the header or inside of the zip
+    pub fn gamestate_reader(&self) -> Result<Box<dyn Read>, String> {
+        let mut file = BufReader::new(File::open(&self.path).map_err(|e| e.to_string())?);
+        file.seek(SeekFrom::Start(self.header.length)).map_err(|e| e.to_string())?;
+
+        // Unified saves put the text metadata before the zip
+        let zip_start = self.header.length + self.header.metadata_size;
+        if self.header.kind == SaveKind::UnifiedText {
+            file.seek(SeekFrom::Start(zip_start)).map_err(|e| e.to_string())?;
+        }
+        if read_u32(&mut file).ok() != Some(ZIP_LOCAL_FILE) {
+            // Not a zip, the gamestate is the rest of the file
+            file.seek(SeekFrom::Start(self.header.length)).map_err(|e| e.to_string())?;
+            return Ok(Box::new(file));
+        }
+        file.seek(SeekFrom::Current(-4)).map_err(|e| e.to_string())?;
+        find_zip_entry(file, GAMESTATE_ENTRY)
+    }
+
+    pub fn gamestate(&self) -> Result<EventReader<Box<dyn Read>>, String> {
+        Ok(EventReader::new(self.gamestate_reader()?))
+    }
+
+    // The meta_data block (date, player country...) without going through the rest of the save
+    pub fn metadata(&self) -> Result<Tree, String> {
+        if self.header.kind == SaveKind::UnifiedText {
+            // The metadata section is the text between the header and the zip
+            let mut file = File::open(&self.path).map_err(|e| e.to_string())?;
+            file.seek(SeekFrom::Start(self.header.length)).map_err(|e| e.to_string())?;
+            let metadata = Parser::with_root("meta_data").parse_reader(file.take(self.header.metadata_size))?;
+            return Ok(metadata.get("meta_data").unwrap_or(metadata));
+        }
+        let mut events = self.gamestate()?;
+        while let Some(event) = events.next_event()? {
+            match event {
+                Event::BeginBlock(key) if key == "meta_data" => return events.read_block(&key),
+                Event::BeginBlock(_) => events.skip_block()?,
+                _ => {}
+            }
+        }
+        Err("No meta_data in the save".to_string())
+    }
+}
+
+fn read_u16(reader: &mut impl Read) -> Result<u16, String> {
+    let mut bytes = [0; 2];
+    reader.read_exact(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, String> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+// Walks the local file headers of the zip until the entry is found
+// Only stored (uncompressed) entries can be read, that's what debug mode writes
+fn find_zip_entry(mut file: BufReader<File>, name: &str) -> Result<Box<dyn Read>, String> {
+    while read_u32(&mut file)? == ZIP_LOCAL_FILE {
+        let _version = read_u16(&mut file)?;
+        let flags = read_u16(&mut file)?;
+        let method = read_u16(&mut file)?;
+        let _time = read_u16(&mut file)?;
+        let _date = read_u16(&mut file)?;
+        let _crc = read_u32(&mut file)?;
+        let mut compressed_size = read_u32(&mut file)? as u64;
+        let _uncompressed_size = read_u32(&mut file)?;
+        let name_length = read_u16(&mut file)? as usize;
+        let extra_length = read_u16(&mut file)? as usize;
+
+        let mut entry_name = vec![0; name_length];
+        file.read_exact(&mut entry_name).map_err(|e| e.to_string())?;
+        let mut extra = vec![0; extra_length];
+        file.read_exact(&mut extra).map_err(|e| e.to_string())?;
+
+        // Zip64 entries store their sizes in the extra field
+        if compressed_size == u32::MAX as u64 {
+            compressed_size = zip64_compressed_size(&extra).ok_or("Invalid zip64 entry")?;
+        }
+        if flags & 0x8 != 0 {
+            return Err("Zip entries with a data descriptor are not supported".to_string());
+        }
+
+        if entry_name == name.as_bytes() {
+            if method != 0 {
+                return Err("The gamestate is compressed, save the game in debug mode to get a plaintext save".to_string());
+            }
+            return Ok(Box::new(file.take(compressed_size)));
+        }
+        file.seek(SeekFrom::Current(compressed_size as i64)).map_err(|e| e.to_string())?;
+    }
+    Err(format!("No {} in the save", name))
+}
+
+fn zip64_compressed_size(extra: &[u8]) -> Option<u64> {
+    let mut rest = extra;
+    while rest.len() >= 4 {
+        let id = u16::from_le_bytes([rest[0], rest[1]]);
+        let size = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+        let data = rest.get(4..4 + size)?;
+        if id == 0x0001 && data.len() >= 16 {
+            // Uncompressed size comes first, then the compressed size
+            return Some(u64::from_le_bytes(data[8..16].try_into().ok()?));
+        }
+        rest = &rest[4 + size..];
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Both saves have the same gamestate, see tests/fixtures
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+    }
+
+    #[test]
+    fn header() {
+        let header = SaveHeader::parse("SAV0102b4a0e6c10004c1a000000000\n").unwrap();
+        assert_eq!(header.get_version(), 1);
+        assert_eq!(header.get_kind(), SaveKind::UnifiedText);
+        assert_eq!(header.get_metadata_size(), 0x4c1a0);
+        assert_eq!(header.length, 32);
+
+        assert_eq!(SaveHeader::parse("SAV0103b4a0e6c10004c1a000000000").unwrap().get_kind(), SaveKind::UnifiedBinary);
+        assert!(SaveHeader::parse("meta_data={").is_err());
+    }
+
+    #[test]
+    fn metadata_size_of_unified_save() {
+        let save = SaveFile::open(fixture("save_unified.v3")).unwrap();
+        assert_eq!(save.get_header().get_kind(), SaveKind::UnifiedText);
+        let metadata = "meta_data={\n\tgame_date=1836.1.1\n\tplayer_country_name=\"GBR\"\n}\n";
+        assert_eq!(save.get_header().get_metadata_size(), metadata.len() as u64);
+    }
+
+    #[test]
+    fn metadata() {
+        for name in ["save_text.v3", "save_unified.v3"] {
+            let metadata = SaveFile::open(fixture(name)).unwrap().metadata().unwrap();
+            assert_eq!(metadata.get("game_date").unwrap().value().unwrap(), "1836.1.1", "{}", name);
+            assert_eq!(metadata.get("player_country_name").unwrap().value().unwrap(), "\"GBR\"", "{}", name);
+        }
+    }
+
+    #[test]
+    fn gamestate_in_zip() {
+        // The zip has a meta entry before the gamestate one
+        let save = SaveFile::open(fixture("save_unified.v3")).unwrap();
+        let mut text = String::new();
+        save.gamestate_reader().unwrap().read_to_string(&mut text).unwrap();
+        assert!(text.starts_with("meta_data={"));
+        assert!(text.ends_with("market_manager={\n\tdatabase={\n\t\t1={ prices={ steel=60 iron=40 } }\n\t\t2={ prices={ steel=50 iron=36 } }\n\t}\n}\n"));
+
+        let mut other = String::new();
+        SaveFile::open(fixture("save_text.v3")).unwrap().gamestate_reader().unwrap().read_to_string(&mut other).unwrap();
+        assert_eq!(text, other);
+    }
+
+    #[test]
+    fn top_level_blocks() {
+        for name in ["save_text.v3", "save_unified.v3"] {
+            let mut events = SaveFile::open(fixture(name)).unwrap().gamestate().unwrap();
+            let mut blocks = Vec::new();
+            while let Some(event) = events.next_event().unwrap() {
+                if let Event::BeginBlock(key) = event {
+                    blocks.push(key);
+                    events.skip_block().unwrap();
+                }
+            }
+            assert_eq!(blocks, ["meta_data", "country_manager", "pops", "states", "building_manager", "market_manager"], "{}", name);
+        }
+    }
+}
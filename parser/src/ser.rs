@@ -0,0 +1,479 @@
+use serde::ser::{self, Serialize};
+
+use crate::{de::Error, node::Node, Tree};
+
+// Serializes any type implementing Serialize into a Tree in Paradox syntax
+// Structs and maps become blocks, a Vec becomes the same key repeated, tuples become lists,
+// bool becomes yes/no and None fields and empty blocks are left out
+//
+// #[derive(Serialize)]
+// struct Template { activate_law: Vec<String> }
+// gives template = { activate_law = law:law_a activate_law = law:law_b }
+pub fn to_tree<T: Serialize + ?Sized>(value: &T) -> Result<Tree, String> {
+    to_tree_named("root", value)
+}
+
+// Same as to_tree but the root is named, e.g. to write a single scripted effect
+pub fn to_tree_named<T: Serialize + ?Sized>(name: &str, value: &T) -> Result<Tree, String> {
+    let item = value.serialize(ItemSerializer)?;
+    let mut builder = NodeBuilder { unique_id: 0 };
+    let mut root = builder.new_node(name.to_string());
+    match item {
+        Item::Block(entries) => builder.add_entries(&mut root, entries),
+        Item::Scalar(value) => root.add_child(builder.new_node(value)),
+        Item::List(items) => {
+            for item in items {
+                builder.add_list_item(&mut root, item);
+            }
+        }
+        Item::Repeated(items) => {
+            for item in items {
+                builder.add_list_item(&mut root, item);
+            }
+        }
+        Item::Nothing => {}
+    }
+    Ok(Tree::new(root, builder.unique_id + 1))
+}
+
+// Quotes the strings that would otherwise break the syntax
+fn format_string(value: &str) -> String {
+    let needs_quotes = value.is_empty()
+        || value.chars().any(|c| c.is_whitespace() || matches!(c, '{' | '}' | '=' | '#' | '"'));
+    if !needs_quotes || (value.len() > 1 && value.starts_with('"') && value.ends_with('"')) {
+        return value.to_string();
+    }
+    format!("\"{}\"", value.replace('"', "\\\""))
+}
+
+// Intermediate representation, turned into nodes once everything is serialized
+enum Item {
+    Scalar(String),
+    Block(Vec<(String, Item)>),
+    // A tuple, written as { a b c }
+    List(Vec<Item>),
+    // A Vec, written as the same key repeated
+    Repeated(Vec<Item>),
+    // None and unit, the key is not written at all
+    Nothing,
+}
+
+struct NodeBuilder {
+    unique_id: usize,
+}
+
+impl NodeBuilder {
+    fn new_node(&mut self, name: String) -> Node {
+        let node = Node::new(name, self.unique_id);
+        self.unique_id += 1;
+        node
+    }
+
+    fn add_entries(&mut self, parent: &mut Node, entries: Vec<(String, Item)>) {
+        for (key, item) in entries {
+            self.add_entry(parent, key, item);
+        }
+    }
+
+    fn add_entry(&mut self, parent: &mut Node, key: String, item: Item) {
+        match item {
+            Item::Scalar(value) => {
+                let mut node = self.new_node(key);
+                node.add_child(self.new_node(value));
+                parent.add_child(node);
+            }
+            Item::Block(entries) => {
+                let mut node = self.new_node(key);
+                self.add_entries(&mut node, entries);
+                add_unless_empty(parent, node);
+            }
+            Item::List(items) => {
+                let mut node = self.new_node(key);
+                for item in items {
+                    self.add_list_item(&mut node, item);
+                }
+                add_unless_empty(parent, node);
+            }
+            Item::Repeated(items) => {
+                for item in items {
+                    self.add_entry(parent, key.clone(), item);
+                }
+            }
+            Item::Nothing => {}
+        }
+    }
+
+    fn add_list_item(&mut self, parent: &mut Node, item: Item) {
+        match item {
+            Item::Scalar(value) => parent.add_child(self.new_node(value)),
+            Item::Nothing => {}
+            // Blocks inside of a list are anonymous
+            item => self.add_entry(parent, String::new(), item),
+        }
+    }
+}
+
+// Empty blocks can't be told apart from a bare token in a Tree, they are left out
+// A block is empty when none of its entries wrote anything, e.g. a struct with only None fields
+fn add_unless_empty(parent: &mut Node, node: Node) {
+    if !node.children().is_empty() {
+        parent.add_child(node);
+    }
+}
+
+struct ItemSerializer;
+
+macro_rules! serialize_display {
+    ($($method:ident: $ty:ty),*) => {
+        $(
+            fn $method(self, value: $ty) -> Result<Item, Error> {
+                Ok(Item::Scalar(value.to_string()))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for ItemSerializer {
+    type Ok = Item;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = VariantSerializer<SeqSerializer>;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = VariantSerializer<MapSerializer>;
+
+    serialize_display! {
+        serialize_i8: i8, serialize_i16: i16, serialize_i32: i32, serialize_i64: i64,
+        serialize_u8: u8, serialize_u16: u16, serialize_u32: u32, serialize_u64: u64,
+        serialize_f32: f32, serialize_f64: f64
+    }
+
+    fn serialize_bool(self, value: bool) -> Result<Item, Error> {
+        Ok(Item::Scalar(if value { "yes" } else { "no" }.to_string()))
+    }
+
+    fn serialize_char(self, value: char) -> Result<Item, Error> {
+        Ok(Item::Scalar(format_string(&value.to_string())))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<Item, Error> {
+        Ok(Item::Scalar(format_string(value)))
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<Item, Error> {
+        Ok(Item::Scalar(format_string(&String::from_utf8_lossy(value))))
+    }
+
+    fn serialize_none(self) -> Result<Item, Error> {
+        Ok(Item::Nothing)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Item, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Item, Error> {
+        Ok(Item::Nothing)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Item, Error> {
+        Ok(Item::Nothing)
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<Item, Error> {
+        Ok(Item::Scalar(format_string(variant)))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<Item, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32, variant: &'static str, value: &T) -> Result<Item, Error> {
+        Ok(Item::Block(vec![(variant.to_string(), value.serialize(ItemSerializer)?)]))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer { items: Vec::with_capacity(len.unwrap_or(0)), repeated: true })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer { items: Vec::with_capacity(len), repeated: false })
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SeqSerializer, Error> {
+        self.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, variant: &'static str, len: usize) -> Result<VariantSerializer<SeqSerializer>, Error> {
+        Ok(VariantSerializer { variant, inner: self.serialize_tuple(len)? })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<MapSerializer, Error> {
+        Ok(MapSerializer { entries: Vec::with_capacity(len.unwrap_or(0)), key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<MapSerializer, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str, len: usize) -> Result<VariantSerializer<MapSerializer>, Error> {
+        Ok(VariantSerializer { variant, inner: self.serialize_map(Some(len))? })
+    }
+}
+
+struct SeqSerializer {
+    items: Vec<Item>,
+    repeated: bool,
+}
+
+impl SeqSerializer {
+    fn finish(self) -> Item {
+        if self.repeated {
+            Item::Repeated(self.items)
+        } else {
+            Item::List(self.items)
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let item = match value.serialize(ItemSerializer)? {
+            // A Vec inside of a Vec can't be a repeated key, it's written as an anonymous block
+            Item::Repeated(items) => Item::List(items),
+            item => item,
+        };
+        self.items.push(item);
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Item, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Item, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Item, Error> {
+        Ok(self.finish())
+    }
+}
+
+struct MapSerializer {
+    entries: Vec<(String, Item)>,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        match key.serialize(ItemSerializer)? {
+            Item::Scalar(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => Err(Error("Keys must be strings or numbers".to_string())),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().ok_or_else(|| Error("Value without a key".to_string()))?;
+        self.entries.push((key, value.serialize(ItemSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Item, Error> {
+        Ok(Item::Block(self.entries))
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.entries.push((key.to_string(), value.serialize(ItemSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Item, Error> {
+        Ok(Item::Block(self.entries))
+    }
+}
+
+// Enum variants with content are written as variant = { ... }
+struct VariantSerializer<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl ser::SerializeTupleVariant for VariantSerializer<SeqSerializer> {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.inner.push(value)
+    }
+
+    fn end(self) -> Result<Item, Error> {
+        Ok(Item::Block(vec![(self.variant.to_string(), self.inner.finish())]))
+    }
+}
+
+impl ser::SerializeStructVariant for VariantSerializer<MapSerializer> {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Item, Error> {
+        Ok(Item::Block(vec![(self.variant.to_string(), Item::Block(self.inner.entries))]))
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{de::from_tree, Parser};
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Country {
+        name: String,
+        #[serde(default)]
+        tier: Option<String>,
+        #[serde(default)]
+        is_landlocked: bool,
+        #[serde(default)]
+        activate_law: Vec<String>,
+        #[serde(default)]
+        color: (u8, u8, u8),
+        #[serde(default)]
+        capital: Option<Capital>,
+    }
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Capital {
+        state: String,
+        #[serde(default)]
+        province: Option<String>,
+    }
+
+    #[derive(Debug, Serialize)]
+    struct Template {
+        activate_law: Vec<String>,
+    }
+
+    // to_tree -> serialize -> parse -> from_tree
+    fn round_trip<T: Serialize + serde::de::DeserializeOwned>(value: &T) -> (String, T) {
+        let text = to_tree(value).unwrap().serialize();
+        let parsed = from_tree(&Parser::new().parse(text.clone())).unwrap();
+        (text, parsed)
+    }
+
+    #[test]
+    fn structs_options_and_repeated_keys() {
+        let countries = BTreeMap::from([
+            ("SWE".to_string(), Country {
+                name: "Sweden".to_string(),
+                tier: Some("kingdom".to_string()),
+                is_landlocked: false,
+                activate_law: vec!["law:law_monarchy".to_string(), "law:law_serfdom".to_string()],
+                color: (20, 60, 200),
+                capital: Some(Capital { state: "STATE_SVEALAND".to_string(), province: None }),
+            }),
+            ("GER".to_string(), Country { name: "Germany".to_string(), is_landlocked: true, ..Default::default() }),
+        ]);
+        let (text, parsed) = round_trip(&countries);
+        assert_eq!(parsed, countries);
+        assert!(text.contains("activate_law = law:law_monarchy\n  activate_law = law:law_serfdom"));
+        assert!(text.contains("is_landlocked = yes"));
+        assert!(text.contains("color = {\n    20\n    60\n    200\n  }"));
+        // None is not written at all
+        assert_eq!(text.matches("tier").count(), 1);
+        assert!(!text.contains("province"));
+    }
+
+    #[test]
+    fn quoted_strings() {
+        let names = BTreeMap::from([
+            ("plain".to_string(), "word".to_string()),
+            ("spaces".to_string(), "Kingdom of Sweden".to_string()),
+            ("quotes".to_string(), "the \"Great\" war".to_string()),
+            ("syntax".to_string(), "a = { b }".to_string()),
+            ("empty".to_string(), String::new()),
+        ]);
+        let (text, parsed) = round_trip(&names);
+        assert_eq!(parsed, names);
+        assert!(text.contains("plain = word\n"));
+        assert!(text.contains("spaces = \"Kingdom of Sweden\"\n"));
+        assert!(text.contains(r#"quotes = "the \"Great\" war""#));
+    }
+
+    #[test]
+    fn empty_blocks_left_out() {
+        // A key without anything in it would be read back as a bare token
+        let templates = BTreeMap::from([
+            ("empty_template".to_string(), Template { activate_law: vec![] }),
+            ("template".to_string(), Template { activate_law: vec!["law:law_monarchy".to_string()] }),
+        ]);
+        assert_eq!(to_tree(&templates).unwrap().serialize(), "template = {\n  activate_law = law:law_monarchy\n}\n");
+
+        // Nested blocks that end up empty are left out with their parents
+        let countries = BTreeMap::from([
+            ("SWE".to_string(), BTreeMap::from([("capital".to_string(), BTreeMap::from([("province".to_string(), None::<String>)]))])),
+            ("GER".to_string(), BTreeMap::from([("capital".to_string(), BTreeMap::from([("province".to_string(), Some("x100".to_string()))]))])),
+        ]);
+        assert_eq!(to_tree(&countries).unwrap().serialize(), "GER = {\n  capital = {\n    province = x100\n  }\n}\n");
+
+        #[derive(Serialize)]
+        struct Wrapper {
+            inner: BTreeMap<String, Option<String>>,
+            tuple: (Option<u8>,),
+            value: u8,
+        }
+        let wrapper = Wrapper { inner: BTreeMap::from([("a".to_string(), None)]), tuple: (None,), value: 1 };
+        assert_eq!(to_tree(&wrapper).unwrap().serialize(), "value = 1\n");
+    }
+}
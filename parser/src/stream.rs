@@ -0,0 +1,392 @@
+use std::{collections::VecDeque, io::{BufRead, BufReader, Read}};
+
+use crate::{Parser, Tree};
+
+// A single step of the pull parser
+// key = { ... } gives BeginBlock(key), the content of the block and then EndBlock
+// Anonymous blocks (a { ... } inside of a list) have an empty key
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    BeginBlock(String),
+    KeyValue(String, String),
+    Token(String),
+    EndBlock,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Equal,
+    Open,
+    Close,
+}
+
+// Splits the input into words, = and braces
+// Quoted strings are a single word (quotes included) and comments are skipped
+struct Lexer<R: Read> {
+    reader: BufReader<R>,
+    peeked: VecDeque<Token>,
+    // Set when a word was ended by ?=
+    pending_equal: bool,
+    finished: bool,
+}
+
+impl<R: Read> Lexer<R> {
+    fn new(reader: R) -> Lexer<R> {
+        Lexer {
+            reader: BufReader::new(reader),
+            peeked: VecDeque::new(),
+            pending_equal: false,
+            finished: false,
+        }
+    }
+
+    fn peek(&mut self, n: usize) -> Result<Option<&Token>, String> {
+        while self.peeked.len() <= n {
+            match self.read_token()? {
+                Some(token) => self.peeked.push_back(token),
+                None => return Ok(None),
+            }
+        }
+        Ok(self.peeked.get(n))
+    }
+
+    fn next(&mut self) -> Result<Option<Token>, String> {
+        if let Some(token) = self.peeked.pop_front() {
+            return Ok(Some(token));
+        }
+        self.read_token()
+    }
+
+    fn peek_byte(&mut self) -> Result<Option<u8>, String> {
+        if self.finished {
+            return Ok(None);
+        }
+        let buffer = self.reader.fill_buf().map_err(|e| e.to_string())?;
+        match buffer.first() {
+            Some(byte) => Ok(Some(*byte)),
+            None => {
+                self.finished = true;
+                Ok(None)
+            }
+        }
+    }
+
+    fn next_byte(&mut self) -> Result<Option<u8>, String> {
+        let byte = self.peek_byte()?;
+        if byte.is_some() {
+            self.reader.consume(1);
+        }
+        Ok(byte)
+    }
+
+    fn read_token(&mut self) -> Result<Option<Token>, String> {
+        if self.pending_equal {
+            self.pending_equal = false;
+            return Ok(Some(Token::Equal));
+        }
+
+        // Skip whitespace, byte order marks and comments
+        loop {
+            match self.peek_byte()? {
+                None => return Ok(None),
+                Some(b'#') => {
+                    while !matches!(self.next_byte()?, Some(b'\n') | None) {}
+                }
+                Some(byte) if byte.is_ascii_whitespace() => {
+                    self.next_byte()?;
+                }
+                Some(0xEF) => {
+                    // Byte order mark (EF BB BF)
+                    let buffer = self.reader.fill_buf().map_err(|e| e.to_string())?;
+                    if buffer.starts_with(&[0xEF, 0xBB, 0xBF]) {
+                        self.reader.consume(3);
+                    } else {
+                        break;
+                    }
+                }
+                Some(_) => break,
+            }
+        }
+
+        let mut word = Vec::new();
+        match self.next_byte()? {
+            Some(b'{') => return Ok(Some(Token::Open)),
+            Some(b'}') => return Ok(Some(Token::Close)),
+            Some(b'=') => return Ok(Some(Token::Equal)),
+            Some(b'?') if self.peek_byte()? == Some(b'=') => {
+                self.next_byte()?;
+                return Ok(Some(Token::Equal));
+            }
+            Some(b'"') => {
+                word.push(b'"');
+                while let Some(byte) = self.next_byte()? {
+                    word.push(byte);
+                    if byte == b'\\' {
+                        // Escaped character, e.g. \"
+                        if let Some(escaped) = self.next_byte()? {
+                            word.push(escaped);
+                        }
+                    } else if byte == b'"' {
+                        break;
+                    }
+                }
+            }
+            Some(byte) => {
+                word.push(byte);
+                while let Some(byte) = self.peek_byte()? {
+                    if byte.is_ascii_whitespace() || matches!(byte, b'{' | b'}' | b'=' | b'#' | b'"') {
+                        break;
+                    }
+                    self.next_byte()?;
+                    // ?= ends the word, a lone ? is part of it
+                    if byte == b'?' && self.peek_byte()? == Some(b'=') {
+                        self.next_byte()?;
+                        self.pending_equal = true;
+                        break;
+                    }
+                    word.push(byte);
+                }
+            }
+            None => return Ok(None),
+        }
+        Ok(Some(Token::Word(String::from_utf8_lossy(&word).into_owned())))
+    }
+}
+
+// Pull based parser over any reader
+// Only the current token is kept in memory so it can go through files of any size
+pub struct EventReader<R: Read> {
+    lexer: Lexer<R>,
+    depth: usize,
+}
+
+impl<R: Read> EventReader<R> {
+    pub fn new(reader: R) -> EventReader<R> {
+        EventReader {
+            lexer: Lexer::new(reader),
+            depth: 0,
+        }
+    }
+
+    // How many blocks the reader is currently in
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn next_event(&mut self) -> Result<Option<Event>, String> {
+        loop {
+            let Some(token) = self.lexer.next()? else {
+                // Close the blocks that were left open
+                if self.depth > 0 {
+                    self.depth -= 1;
+                    return Ok(Some(Event::EndBlock));
+                }
+                return Ok(None);
+            };
+            match token {
+                Token::Open => {
+                    self.depth += 1;
+                    return Ok(Some(Event::BeginBlock(String::new())));
+                }
+                Token::Close => {
+                    // A closing brace without an opening one is ignored
+                    if self.depth > 0 {
+                        self.depth -= 1;
+                        return Ok(Some(Event::EndBlock));
+                    }
+                }
+                Token::Equal => {
+                    // An = without a key is ignored
+                }
+                Token::Word(word) => {
+                    if self.lexer.peek(0)? != Some(&Token::Equal) {
+                        return Ok(Some(Event::Token(word)));
+                    }
+                    match self.lexer.peek(1)? {
+                        Some(Token::Open) => {
+                            self.lexer.next()?;
+                            self.lexer.next()?;
+                            self.depth += 1;
+                            return Ok(Some(Event::BeginBlock(word)));
+                        }
+                        Some(Token::Word(_)) => {
+                            self.lexer.next()?;
+                            if let Some(Token::Word(value)) = self.lexer.next()? {
+                                return Ok(Some(Event::KeyValue(word, value)));
+                            }
+                        }
+                        _ => {
+                            // key = followed by nothing or a closing brace
+                            self.lexer.next()?;
+                            return Ok(Some(Event::Token(word)));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Skips everything until the end of the current block
+    // Call this right after a BeginBlock to ignore its content
+    pub fn skip_block(&mut self) -> Result<(), String> {
+        let depth = self.depth;
+        while let Some(event) = self.next_event()? {
+            if event == Event::EndBlock && self.depth < depth {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // Builds a Tree out of the current block
+    // Call this right after a BeginBlock, the root of the tree is named after the block
+    pub fn read_block(&mut self, name: &str) -> Result<Tree, String> {
+        let depth = self.depth;
+        let mut parser = Parser::with_root(name);
+        while let Some(event) = self.next_event()? {
+            if event == Event::EndBlock && self.depth < depth {
+                break;
+            }
+            parser.push(event);
+        }
+        Ok(parser.finish())
+    }
+}
+
+impl<R: Read> Iterator for EventReader<R> {
+    type Item = Result<Event, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(text: &str) -> Vec<Token> {
+        let mut lexer = Lexer::new(text.as_bytes());
+        let mut tokens = Vec::new();
+        while let Some(token) = lexer.next().unwrap() {
+            tokens.push(token);
+        }
+        tokens
+    }
+
+    fn events(text: &str) -> Vec<Event> {
+        EventReader::new(text.as_bytes()).collect::<Result<Vec<Event>, String>>().unwrap()
+    }
+
+    fn word(text: &str) -> Token {
+        Token::Word(text.to_string())
+    }
+
+    fn begin(key: &str) -> Event {
+        Event::BeginBlock(key.to_string())
+    }
+
+    fn key_value(key: &str, value: &str) -> Event {
+        Event::KeyValue(key.to_string(), value.to_string())
+    }
+
+    fn token(text: &str) -> Event {
+        Event::Token(text.to_string())
+    }
+
+    #[test]
+    fn lexer_splits_words_equals_and_braces() {
+        assert_eq!(tokens("a=b c = { d }"), vec![word("a"), Token::Equal, word("b"), word("c"), Token::Equal, Token::Open, word("d"), Token::Close]);
+        assert_eq!(tokens("a={b}"), vec![word("a"), Token::Equal, Token::Open, word("b"), Token::Close]);
+    }
+
+    #[test]
+    fn lexer_keeps_quoted_strings_whole() {
+        assert_eq!(tokens(r#"name = "a b = { c }""#), vec![word("name"), Token::Equal, word(r#""a b = { c }""#)]);
+    }
+
+    #[test]
+    fn lexer_keeps_escaped_quotes_in_strings() {
+        // The escapes are kept as they are, de::unquote removes them
+        assert_eq!(tokens(r#"name = "say \"hi\"" next"#), vec![word("name"), Token::Equal, word(r#""say \"hi\"""#), word("next")]);
+        assert_eq!(tokens(r#"a = "\"" b = "c\\""#), vec![word("a"), Token::Equal, word(r#""\"""#), word("b"), Token::Equal, word(r#""c\\""#)]);
+    }
+
+    #[test]
+    fn lexer_skips_comments_and_byte_order_mark() {
+        assert_eq!(tokens("\u{feff}a = b # c = d\ne = f#g\n# h"), vec![word("a"), Token::Equal, word("b"), word("e"), Token::Equal, word("f")]);
+        assert_eq!(tokens(r#"a = "b # not a comment""#), vec![word("a"), Token::Equal, word(r#""b # not a comment""#)]);
+    }
+
+    #[test]
+    fn lexer_reads_question_equal_as_equal() {
+        assert_eq!(tokens("a ?= b"), vec![word("a"), Token::Equal, word("b")]);
+        assert_eq!(tokens("a?=b"), vec![word("a"), Token::Equal, word("b")]);
+        // A lone ? is part of the word
+        assert_eq!(tokens("a? b"), vec![word("a?"), word("b")]);
+    }
+
+    #[test]
+    fn events_of_nested_blocks() {
+        let text = "a = { b = c d = { e f } } g = h";
+        assert_eq!(events(text), vec![
+            begin("a"),
+            key_value("b", "c"),
+            begin("d"),
+            token("e"),
+            token("f"),
+            Event::EndBlock,
+            Event::EndBlock,
+            key_value("g", "h"),
+        ]);
+    }
+
+    #[test]
+    fn events_of_anonymous_blocks() {
+        assert_eq!(events("list = { { a = b } c }"), vec![
+            begin("list"),
+            begin(""),
+            key_value("a", "b"),
+            Event::EndBlock,
+            token("c"),
+            Event::EndBlock,
+        ]);
+    }
+
+    #[test]
+    fn unbalanced_braces() {
+        // Blocks left open are closed at the end
+        assert_eq!(events("a = { b = c"), vec![begin("a"), key_value("b", "c"), Event::EndBlock]);
+        // Closing braces without an opening one are dropped
+        assert_eq!(events("} a = b }"), vec![key_value("a", "b")]);
+        // key = without a value is a bare token
+        assert_eq!(events("a = { b = }"), vec![begin("a"), token("b"), Event::EndBlock]);
+    }
+
+    #[test]
+    fn skip_and_read_block() {
+        let mut reader = EventReader::new("a = { b = { c = d } e = f } g = h".as_bytes());
+        assert_eq!(reader.next_event().unwrap(), Some(begin("a")));
+        assert_eq!(reader.next_event().unwrap(), Some(begin("b")));
+        reader.skip_block().unwrap();
+        assert_eq!(reader.next_event().unwrap(), Some(key_value("e", "f")));
+        assert_eq!(reader.next_event().unwrap(), Some(Event::EndBlock));
+        assert_eq!(reader.depth(), 0);
+
+        let mut reader = EventReader::new("a = { b = c } d = e".as_bytes());
+        assert_eq!(reader.next_event().unwrap(), Some(begin("a")));
+        let tree = reader.read_block("a").unwrap();
+        assert_eq!(tree.get("b").unwrap().value().unwrap(), "c");
+        assert_eq!(reader.next_event().unwrap(), Some(key_value("d", "e")));
+    }
+
+    // The tree of a production method file is the same as the one of the parser this one replaced
+    // production_methods.expected.txt is what the old parser gave for it
+    #[test]
+    fn same_tree_as_the_old_parser() {
+        let text = include_str!("../tests/fixtures/production_methods.txt");
+        let expected = include_str!("../tests/fixtures/production_methods.expected.txt");
+        assert_eq!(Parser::new().parse(text.to_string()).serialize(), expected);
+    }
+}
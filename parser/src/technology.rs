@@ -0,0 +1,273 @@
+use std::{collections::{BTreeSet, HashMap, HashSet}, fmt::{self, Display, Formatter}};
+
+use serde::Deserialize;
+
+use crate::{de::from_tree, utils::{generate_tree, get_paths}, Tree};
+
+pub const TECHNOLOGIES_FOLDER: &str = r#"\common\technology\technologies"#;
+
+// A single entry of common/technology/technologies
+#[derive(Debug, Clone, Deserialize)]
+pub struct Technology {
+    #[serde(default)]
+    era: String,
+    // production, military or society
+    #[serde(default)]
+    category: String,
+    // The technologies that have to be researched first
+    #[serde(default)]
+    unlocking_technologies: Vec<String>,
+}
+
+impl Technology {
+    // era_3 -> 3, 0 when the era is missing
+    pub fn get_era(&self) -> u32 {
+        self.era.trim_start_matches("era_").parse().unwrap_or(0)
+    }
+
+    pub fn get_category(&self) -> &str {
+        &self.category
+    }
+
+    pub fn get_prerequisites(&self) -> &Vec<String> {
+        &self.unlocking_technologies
+    }
+}
+
+// Something wrong with the prerequisites, the game would never let these be researched
+#[derive(Debug, Clone, PartialEq)]
+pub enum TechnologyIssue {
+    MissingPrerequisite { technology: String, prerequisite: String },
+    // Every technology of the cycle, the first one is repeated at the end
+    Cycle(Vec<String>),
+}
+
+impl Display for TechnologyIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TechnologyIssue::MissingPrerequisite { technology, prerequisite } => write!(f, "{} needs {} which does not exist", technology, prerequisite),
+            TechnologyIssue::Cycle(cycle) => write!(f, "cycle of prerequisites: {}", cycle.join(" -> ")),
+        }
+    }
+}
+
+// Every technology, each one points to its prerequisites
+// The game expects this to be a DAG, see validate
+#[derive(Debug, Clone, Default)]
+pub struct Technologies {
+    technologies: HashMap<String, Technology>,
+}
+
+impl Technologies {
+    pub fn new(technologies: HashMap<String, Technology>) -> Technologies {
+        Technologies { technologies }
+    }
+
+    pub fn from_tree(tree: &Tree) -> Result<Technologies, String> {
+        let mut technologies = HashMap::new();
+        for child in tree.clone() {
+            technologies.insert(child.get_name(), from_tree(&child)?);
+        }
+        Ok(Technologies { technologies })
+    }
+
+    // The mod files replace the game files with the same name
+    pub fn load(mod_path: &str, game_path: &str) -> Result<Technologies, String> {
+        Technologies::from_tree(&generate_tree(get_paths(mod_path, game_path, TECHNOLOGIES_FOLDER)))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Technology> {
+        self.technologies.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.technologies.contains_key(name)
+    }
+
+    pub fn names(&self) -> Vec<&String> {
+        let mut names: Vec<&String> = self.technologies.keys().collect();
+        names.sort();
+        names
+    }
+
+    pub fn len(&self) -> usize {
+        self.technologies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.technologies.is_empty()
+    }
+
+    // The technologies and everything needed to research them
+    pub fn closure(&self, technologies: &[String]) -> HashSet<String> {
+        let mut result = HashSet::new();
+        let mut stack: Vec<String> = technologies.to_vec();
+        while let Some(technology) = stack.pop() {
+            if !result.insert(technology.clone()) {
+                continue;
+            }
+            if let Some(definition) = self.technologies.get(&technology) {
+                stack.extend(definition.get_prerequisites().iter().cloned());
+            }
+        }
+        result
+    }
+
+    // Everything needed before the technology, without the technology itself
+    pub fn prerequisites_of(&self, technology: &str) -> HashSet<String> {
+        let mut closure = self.closure(&[technology.to_string()]);
+        closure.remove(technology);
+        closure
+    }
+
+    // The era a technology can be researched in at the earliest
+    // A prerequisite from a later era pushes it back to that era
+    pub fn earliest_era(&self, technology: &str) -> Option<u32> {
+        self.technologies.get(technology)?;
+        self.closure(&[technology.to_string()]).iter()
+            .filter_map(|t| self.technologies.get(t))
+            .map(|t| t.get_era())
+            .max()
+    }
+
+    // Every technology of this era and the ones before it
+    pub fn up_to_era(&self, era: u32) -> HashSet<String> {
+        self.technologies.iter().filter(|(_, t)| t.get_era() <= era).map(|(name, _)| name.clone()).collect()
+    }
+
+    // The technologies and their prerequisites in an order they can be researched in
+    // Prerequisites come first, ties are broken by era and then by name
+    // Technologies that don't exist or are part of a cycle can never be researched and are left out
+    pub fn research_order(&self, technologies: &[String]) -> Vec<String> {
+        let mut needed = self.closure(technologies);
+        needed.retain(|t| self.technologies.contains_key(t));
+        let prerequisites = |technology: &String| -> Vec<&String> {
+            self.technologies.get(technology)
+                .map(|t| t.get_prerequisites().iter().filter(|p| needed.contains(*p)).collect())
+                .unwrap_or_default()
+        };
+        let mut remaining: HashMap<&String, usize> = needed.iter().map(|t| (t, prerequisites(t).len())).collect();
+        let mut ready: BTreeSet<(u32, &String)> = remaining.iter().filter(|(_, count)| **count == 0).map(|(t, _)| (self.era_of(t), *t)).collect();
+        let mut order = Vec::new();
+        while let Some((era, technology)) = ready.iter().next().cloned() {
+            ready.remove(&(era, technology));
+            remaining.remove(technology);
+            order.push(technology.clone());
+            for (other, count) in remaining.iter_mut() {
+                if prerequisites(other).contains(&technology) {
+                    *count -= 1;
+                    if *count == 0 {
+                        ready.insert((self.era_of(other), *other));
+                    }
+                }
+            }
+        }
+        order
+    }
+
+    // Prerequisites that don't exist and cycles of prerequisites
+    pub fn validate(&self) -> Vec<TechnologyIssue> {
+        let mut issues = Vec::new();
+        for name in self.names() {
+            for prerequisite in self.technologies[name].get_prerequisites() {
+                if !self.technologies.contains_key(prerequisite) {
+                    issues.push(TechnologyIssue::MissingPrerequisite { technology: name.clone(), prerequisite: prerequisite.clone() });
+                }
+            }
+        }
+        // Depth first search, a technology that is reached again while it is still on the path closes a cycle
+        let mut done: HashSet<&String> = HashSet::new();
+        for start in self.names() {
+            if done.contains(start) {
+                continue;
+            }
+            let mut path: Vec<(&String, usize)> = vec![(start, 0)];
+            while let Some((technology, next)) = path.last().cloned() {
+                let prerequisites = self.technologies.get(technology).map(|t| t.get_prerequisites().as_slice()).unwrap_or(&[]);
+                let Some(prerequisite) = prerequisites.get(next) else {
+                    done.insert(technology);
+                    path.pop();
+                    continue;
+                };
+                path.last_mut().unwrap().1 += 1;
+                if let Some(position) = path.iter().position(|(t, _)| *t == prerequisite) {
+                    let mut cycle: Vec<String> = path[position..].iter().map(|(t, _)| t.to_string()).collect();
+                    cycle.push(prerequisite.clone());
+                    issues.push(TechnologyIssue::Cycle(cycle));
+                } else if !done.contains(prerequisite) && self.technologies.contains_key(prerequisite) {
+                    path.push((prerequisite, 0));
+                }
+            }
+        }
+        issues
+    }
+
+    // Unknown technologies are sorted with the first era
+    fn era_of(&self, technology: &str) -> u32 {
+        self.technologies.get(technology).map(|t| t.get_era()).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Parser;
+
+    use super::*;
+
+    fn technologies(text: &str) -> Technologies {
+        Technologies::from_tree(&Parser::new().parse(text.to_string())).unwrap()
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn missing_prerequisite() {
+        let technologies = technologies("
+            railways = { era = era_2 unlocking_technologies = { steam_engine steelworking } }
+            steam_engine = { era = era_1 }
+        ");
+        assert_eq!(technologies.validate(), vec![TechnologyIssue::MissingPrerequisite { technology: "railways".to_string(), prerequisite: "steelworking".to_string() }]);
+        // The missing one is left out of the order
+        assert_eq!(technologies.research_order(&names(&["railways"])), names(&["steam_engine", "railways"]));
+    }
+
+    #[test]
+    fn cycle() {
+        let technologies = technologies("
+            a = { unlocking_technologies = { c } }
+            b = { unlocking_technologies = { a } }
+            c = { unlocking_technologies = { b } }
+            d = { unlocking_technologies = { a } }
+            e = { }
+        ");
+        assert_eq!(technologies.validate(), vec![TechnologyIssue::Cycle(names(&["a", "c", "b", "a"]))]);
+        assert_eq!(technologies.validate()[0].to_string(), "cycle of prerequisites: a -> c -> b -> a");
+        // Nothing of the cycle can ever be researched, nor what needs it
+        assert_eq!(technologies.research_order(&names(&["d", "e"])), names(&["e"]));
+    }
+
+    #[test]
+    fn research_order_respects_prerequisites() {
+        let technologies = technologies("
+            bessemer_process = { era = era_2 unlocking_technologies = { steelworking railways } }
+            railways = { era = era_2 unlocking_technologies = { steam_engine } }
+            steelworking = { era = era_1 unlocking_technologies = { mechanized_workshops } }
+            steam_engine = { era = era_1 unlocking_technologies = { mechanized_workshops } }
+            mechanized_workshops = { era = era_1 }
+            late = { era = era_3 }
+        ");
+        assert!(technologies.validate().is_empty());
+        let order = technologies.research_order(&names(&["bessemer_process"]));
+        assert_eq!(order, names(&["mechanized_workshops", "steam_engine", "steelworking", "railways", "bessemer_process"]));
+        for (index, technology) in order.iter().enumerate() {
+            for prerequisite in technologies.get(technology).unwrap().get_prerequisites() {
+                assert!(order[..index].contains(prerequisite), "{} before {}", prerequisite, technology);
+            }
+        }
+
+        assert_eq!(technologies.earliest_era("bessemer_process"), Some(2));
+        assert_eq!(technologies.prerequisites_of("railways"), HashSet::from(["steam_engine".to_string(), "mechanized_workshops".to_string()]));
+    }
+}
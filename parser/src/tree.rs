@@ -1,34 +1,57 @@
+use std::{cell::Cell, rc::Rc};
+
 use super::node::Node;
 
 #[derive(Debug)]
 pub struct Tree {
     root: Node,
-    highest_id: usize,
+    // Next free unique id, shared with the trees that share the nodes so new nodes never get the same id
+    highest_id: Rc<Cell<usize>>,
 }
 
+// Like Node, this shares the nodes: editing the clone edits the original
+// Use deep_clone to get an independent copy
 impl Clone for Tree {
     fn clone(&self) -> Self {
         Tree {
             root: self.root.clone(),
-            highest_id: self.highest_id,
+            highest_id: self.highest_id.clone(),
         }
     }
 }
 
+// Handle to a node of a Tree, it stays valid while other nodes are inserted or removed
+// Handles are the unique ids of the nodes, so a handle taken from a tree returned by get or search_child
+// can be used on the tree it comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
 impl Tree {
     pub fn new(root: Node, highest_id: usize) -> Tree {
         Tree {
             root,
-            highest_id
+            highest_id: Rc::new(Cell::new(highest_id))
+        }
+    }
+
+    // Another tree on a node of this one, sharing the nodes and the ids
+    fn view(&self, node: Node) -> Tree {
+        Tree {
+            root: node,
+            highest_id: self.highest_id.clone(),
         }
     }
 
     fn children(&self) -> Vec<Node> {
         self.root.children()
     }
+
+    pub(crate) fn root(&self) -> &Node {
+        &self.root
+    }
     
     pub fn get_highest_id(&self) -> usize {
-        self.highest_id
+        self.highest_id.get()
     }
 
     pub fn get_name(&self) -> String {
@@ -51,6 +74,7 @@ impl Tree {
         }
     }
 
+    // The child is not copied, editing it later also edits this tree
     pub fn add_child_tree(&mut self, child: Tree) {
         self.root.add_child(child.root.clone());
     }
@@ -71,7 +95,7 @@ impl Tree {
         let mut v = vec!["root".to_string()];
         v.extend(name.iter().cloned());
         match self.find_scope(&v, None) {
-            Some(scope) => Ok(Tree::new(scope, self.get_highest_id())),
+            Some(scope) => Ok(self.view(scope)),
             None => Err("Scope not found".to_string())
         }
     }
@@ -94,7 +118,7 @@ impl Tree {
 
 pub struct TreeIterator {
     stack: Vec<Node>,
-    highest_id: usize,
+    highest_id: Rc<Cell<usize>>,
 }
 
 impl Iterator for TreeIterator {
@@ -105,7 +129,7 @@ impl Iterator for TreeIterator {
             return None;
         }
         let node = self.stack.pop().unwrap();
-        Some(Tree::new(node, self.highest_id))
+        Some(Tree { root: node, highest_id: self.highest_id.clone() })
     }
 }
 
@@ -116,7 +140,7 @@ impl IntoIterator for Tree {
     fn into_iter(self) -> Self::IntoIter {
         TreeIterator {
             stack: self.children().iter().cloned().collect(),
-            highest_id: self.get_highest_id(),
+            highest_id: self.highest_id.clone(),
         }
     }
 }
@@ -125,14 +149,14 @@ impl Tree {
     pub fn into_iter_filtered(self, filter: impl Fn(&String) -> bool) -> TreeIterator {
         TreeIterator {
             stack: self.children().iter().cloned().filter(|child| filter(&child.name())).collect(),
-            highest_id: self.get_highest_id(),
+            highest_id: self.highest_id.clone(),
         }
     }
 
     pub fn search_child(&self, filter: impl Fn(&String) -> bool) -> Option<Tree> {
         for child in self.children() {
             if filter(&child.name()) {
-                return Some(Tree::new(child, self.get_highest_id()));
+                return Some(self.view(child));
             }
         }
         None
@@ -144,7 +168,7 @@ impl Tree {
 
     pub fn move_up(&self, key: &str) -> Tree {
         // moves all children of the key node to the root
-        let mut new_root = Node::new("root".to_string(), self.get_highest_id());
+        let mut new_root = Node::new("root".to_string(), self.next_id());
         for child in self.children() {
             if child.name().contains(key) {
                 for grandchild in child.children() {
@@ -152,7 +176,7 @@ impl Tree {
                 }
             }
         }
-        Tree::new(new_root, self.get_highest_id())
+        self.view(new_root)
     }
 }
 
@@ -168,9 +192,11 @@ impl ToString for Tree {
 
 impl Tree {
 
+    // Both trees are copied, the result doesn't share anything with them
     pub fn merge(&self, other: &Tree) -> Tree {
-        let new_tree = self.hard_clone();
-        new_tree.merge_internal(other)
+        let mut new_tree = self.deep_clone();
+        new_tree.extend_from(other);
+        new_tree
     }
 
     pub fn from_key_value(key: &str, value: &str, highest_id: usize) -> Node {
@@ -180,8 +206,9 @@ impl Tree {
         key_node
     }
 
+    // Returns a copy without the matching children, the tree itself is not modified
     pub fn delete_children_filtered(&self, filter: impl Fn(&String) -> bool) -> Tree {
-        let mut new_tree = self.hard_clone();
+        let mut new_tree = self.deep_clone();
         let mut children = new_tree.children();
         children.retain(|child| !filter(&child.name()));
         new_tree.root.set_children(children);
@@ -201,23 +228,14 @@ impl Tree {
         Tree::new(root, highest_id)
     }
 
-    fn hard_clone(&self) -> Tree {
-        let new_tree = Tree::new(self.root.clone(), self.get_highest_id());
-        new_tree
-    }
-
-    fn merge_internal(mut self, other: &Tree) -> Tree {
-        let mut new_uids = self.get_highest_id();
-        for child in &other.root.children() {
-            let mut new_child = child.clone();
-            new_child.set_unique_id(new_uids);
-            new_uids += 1;
-            self.root.add_child(child.clone());
+    // Copies the children of the other tree at the end of this one, with new unique ids
+    pub fn extend_from(&mut self, other: &Tree) {
+        for child in other.root.children() {
+            let copy = self.copy_node(&child);
+            self.root.add_child(copy);
         }
+    }
 
-        return self;
-    }  
-    
     pub fn serialize(&self) -> String {
         let mut result = String::new();
         self.serialize_node(&self.root, &mut result, 0);
@@ -231,12 +249,15 @@ impl Tree {
         if children.is_empty() {
             // It's a leaf node, simply add the node's name
             result.push_str(&format!("{}{}\n", indent, node.name()));
-        } else if children.len() == 1 && node.name() != "root" && children[0].children().is_empty(){
+        } else if children.len() == 1 && node.name() != "root" && !node.name().is_empty() && children[0].children().is_empty(){
             // It's a key-value pair
             result.push_str(&format!("{}{} = {}\n", indent, node.name(), children[0].name()));
         } else {
             // It's a nested structure
-            if node.name() != "root" {
+            if node.name().is_empty() {
+                // Anonymous block inside of a list
+                result.push_str(&format!("{}{{\n", indent));
+            } else if node.name() != "root" {
                 result.push_str(&format!("{}{} = {{\n", indent, node.name()));
             }
             for child in children {
@@ -248,4 +269,216 @@ impl Tree {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// Editing
+// Trees returned by get, search_child and the iterators share their nodes with the tree they come from,
+// editing them edits the original. deep_clone gives a copy that can be edited on its own
+// Trees given to the insert methods are always copied, editing them afterwards doesn't change this tree
+impl Tree {
+    // Copies every node, nothing is shared with this tree
+    // The copy keeps the unique ids so handles of this tree are still valid on the copy
+    pub fn deep_clone(&self) -> Tree {
+        Tree::new(self.root.deep_clone(), self.get_highest_id())
+    }
+
+    fn next_id(&self) -> usize {
+        let id = self.highest_id.get();
+        self.highest_id.set(id + 1);
+        id
+    }
+
+    // Copies a node of another tree with ids that are free in this one
+    fn copy_node(&self, node: &Node) -> Node {
+        let mut next_id = self.highest_id.get();
+        let copy = node.deep_clone_with_ids(&mut next_id);
+        self.highest_id.set(next_id);
+        copy
+    }
+
+    fn node(&self, handle: NodeId) -> Result<Node, String> {
+        self.root.find(handle.0).ok_or_else(|| format!("Node {} not found in {}", handle.0, self.get_name()))
+    }
+
+    fn parent(&self, handle: NodeId) -> Result<(Node, usize), String> {
+        if handle == self.root_handle() {
+            return Err("The root has no parent".to_string());
+        }
+        self.root.find_parent(handle.0).ok_or_else(|| format!("Node {} not found in {}", handle.0, self.get_name()))
+    }
+
+    pub fn root_handle(&self) -> NodeId {
+        NodeId(self.root.unique_id())
+    }
+
+    // The node at the end of the path of keys, the first matching key is used at every level
+    // e.g. tree.handle(&["building_steel_mills", "production_method_groups"])
+    pub fn handle(&self, path: &[&str]) -> Option<NodeId> {
+        let mut current = self.root.clone();
+        for key in path {
+            current = current.children().into_iter().find(|child| child.name() == *key)?;
+        }
+        Some(NodeId(current.unique_id()))
+    }
+
+    // Handles of the direct children of the root whose key matches
+    pub fn child_handles(&self, filter: impl Fn(&String) -> bool) -> Vec<NodeId> {
+        self.children().iter().filter(|child| filter(&child.name())).map(|child| NodeId(child.unique_id())).collect()
+    }
+
+    // The node as a tree, sharing its nodes with this one
+    pub fn get_handle(&self, handle: NodeId) -> Result<Tree, String> {
+        Ok(self.view(self.node(handle)?))
+    }
+
+    // Adds a copy of the tree as the last child of the node, returns the handle of the copy
+    pub fn append(&mut self, parent: NodeId, child: &Tree) -> Result<NodeId, String> {
+        let mut parent = self.node(parent)?;
+        let copy = self.copy_node(&child.root);
+        let handle = NodeId(copy.unique_id());
+        parent.add_child(copy);
+        Ok(handle)
+    }
+
+    // Adds a copy of the tree right before the node, in the same block
+    pub fn insert_before(&mut self, sibling: NodeId, child: &Tree) -> Result<NodeId, String> {
+        let (mut parent, index) = self.parent(sibling)?;
+        let copy = self.copy_node(&child.root);
+        let handle = NodeId(copy.unique_id());
+        parent.insert_child(index, copy);
+        Ok(handle)
+    }
+
+    // Adds a copy of the tree right after the node, in the same block
+    pub fn insert_after(&mut self, sibling: NodeId, child: &Tree) -> Result<NodeId, String> {
+        let (mut parent, index) = self.parent(sibling)?;
+        let copy = self.copy_node(&child.root);
+        let handle = NodeId(copy.unique_id());
+        parent.insert_child(index + 1, copy);
+        Ok(handle)
+    }
+
+    // Changes the value of key = value, a bare key becomes key = value
+    // Blocks don't have a single value so they can't be replaced this way
+    // key = { a } and key = a are the same tree, so a block of a single token is replaced too
+    // and written back as key = b
+    pub fn replace_value(&mut self, handle: NodeId, value: &str) -> Result<(), String> {
+        let mut node = self.node(handle)?;
+        let children = node.children();
+        match children.as_slice() {
+            [] => node.add_child(Node::new(value.to_string(), self.next_id())),
+            [child] if child.children().is_empty() => child.clone().set_name(value.to_string()),
+            _ => return Err(format!("{} is a block, not a value", node.name())),
+        }
+        Ok(())
+    }
+
+    pub fn rename(&mut self, handle: NodeId, name: &str) -> Result<(), String> {
+        self.node(handle)?.set_name(name.to_string());
+        Ok(())
+    }
+
+    // Takes the node out of the tree and returns it, handles of the nodes below it are not valid here anymore
+    pub fn remove(&mut self, handle: NodeId) -> Result<Tree, String> {
+        let (mut parent, index) = self.parent(handle)?;
+        let removed = parent.remove_child(index);
+        Ok(Tree::new(removed, self.get_highest_id()))
+    }
+
+    pub fn remove_path(&mut self, path: &[&str]) -> Result<Tree, String> {
+        let handle = self.handle(path).ok_or_else(|| format!("{} not found in {}", path.join("."), self.get_name()))?;
+        self.remove(handle)
+    }
+
+    // Sets key = value at the end of the path, the missing keys and blocks along the way are created
+    // e.g. tree.set(&["building_steel_mills", "required_construction"], "construction_cost_high")
+    pub fn set(&mut self, path: &[&str], value: &str) -> Result<NodeId, String> {
+        let (last, blocks) = path.split_last().ok_or("Empty path")?;
+        let mut current = self.root.clone();
+        for key in blocks {
+            current = match current.children().into_iter().find(|child| child.name() == *key) {
+                Some(child) => child,
+                None => {
+                    let child = Node::new(key.to_string(), self.next_id());
+                    current.add_child(child.clone());
+                    child
+                }
+            };
+        }
+        let handle = match current.children().into_iter().find(|child| child.name() == *last) {
+            Some(child) => NodeId(child.unique_id()),
+            None => {
+                let child = Node::new(last.to_string(), self.next_id());
+                current.add_child(child.clone());
+                NodeId(child.unique_id())
+            }
+        };
+        self.replace_value(handle, value)?;
+        Ok(handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Parser;
+
+    use super::*;
+
+    fn parse(text: &str) -> Tree {
+        Parser::new().parse(text.to_string())
+    }
+
+    #[test]
+    fn deep_clone_is_independent() {
+        let mut tree = parse("a = { b = c } d = e");
+        let mut copy = tree.deep_clone();
+        copy.set(&["a", "b"], "changed").unwrap();
+        copy.remove_path(&["d"]).unwrap();
+        assert_eq!(tree.serialize(), "a = {\n  b = c\n}\nd = e\n");
+
+        tree.set(&["a", "f"], "g").unwrap();
+        assert_eq!(copy.serialize(), "a = {\n  b = changed\n}\n");
+
+        // A plain clone shares its nodes
+        let mut shared = tree.clone();
+        shared.remove_path(&["d"]).unwrap();
+        assert!(tree.handle(&["d"]).is_none());
+    }
+
+    #[test]
+    fn handles_survive_siblings_changing() {
+        let mut tree = parse("a = 1 b = 2 c = 3");
+        let b = tree.handle(&["b"]).unwrap();
+        let c = tree.handle(&["c"]).unwrap();
+
+        let new = tree.insert_before(b, &Tree::new(Tree::from_key_value("x", "0", 0), 1)).unwrap();
+        tree.insert_after(b, &Tree::new(Tree::from_key_value("y", "0", 0), 1)).unwrap();
+        assert_eq!(tree.get_handle(b).unwrap().value().unwrap(), "2");
+        tree.remove(tree.handle(&["a"]).unwrap()).unwrap();
+        assert_eq!(tree.get_handle(b).unwrap().value().unwrap(), "2");
+        assert_eq!(tree.get_handle(new).unwrap().get_name(), "x");
+
+        tree.replace_value(c, "4").unwrap();
+        assert_eq!(tree.serialize(), "x = 0\nb = 2\ny = 0\nc = 4\n");
+
+        // Removed nodes are gone
+        tree.remove(b).unwrap();
+        assert!(tree.get_handle(b).is_err());
+        assert_eq!(tree.get_handle(c).unwrap().value().unwrap(), "4");
+    }
+
+    #[test]
+    fn replace_value_of_single_token_block() {
+        // The parser gives the same tree for both, replace_value can't tell them apart
+        let mut tree = parse("list = { a } value = a");
+        for key in ["list", "value"] {
+            let handle = tree.handle(&[key]).unwrap();
+            tree.replace_value(handle, "b").unwrap();
+        }
+        assert_eq!(tree.serialize(), "list = b\nvalue = b\n");
+
+        let mut tree = parse("list = { a b }");
+        let handle = tree.handle(&["list"]).unwrap();
+        assert!(tree.replace_value(handle, "c").is_err());
+    }
+}
@@ -43,9 +43,9 @@ pub fn merge_trees(trees: &[Tree]) -> Tree {
     if trees.len() == 0 {
         return Tree::new(Node::new("empty".to_owned(), 0), 1);
     }
-    let mut new_tree = trees[0].clone();
+    let mut new_tree = trees[0].deep_clone();
     for tree in trees.iter().skip(1) {
-        new_tree = new_tree.merge(tree);
+        new_tree.extend_from(tree);
     }
     new_tree
 }
@@ -76,9 +76,9 @@ pub fn generate_trees(paths: Vec<String>) -> Vec<Tree> {
 }
 
 pub fn parse_file(path: &str) -> Tree {
-    let text = std::fs::read_to_string(path).unwrap();
+    let file = std::fs::File::open(path).unwrap();
     let parser = Parser::new();
-    parser.parse(text)
+    parser.parse_reader(file).unwrap()
 }
 
 pub fn get_paths(mod_path: &str, game_path: &str, extension: &str) -> Vec<String> {
@@ -92,16 +92,11 @@ pub fn get_paths(mod_path: &str, game_path: &str, extension: &str) -> Vec<String
 
 pub fn discriminate(mod_path: String, game_path: String) -> Vec<String> {
     // Read the mod files
-    let mod_files = match fs::read_dir(&mod_path) {
-        Ok(files) => files,
-        Err(_) => return Vec::new(),
-    };
+    // A folder the mod doesn't have only means that the game files are used as they are
+    let mod_files = fs::read_dir(&mod_path).into_iter().flatten();
 
     // Read the game files
-    let game_files_raw = match fs::read_dir(&game_path) {
-        Ok(files) => files,
-        Err(_) => return Vec::new(),
-    };
+    let game_files_raw = fs::read_dir(&game_path).into_iter().flatten();
 
     // Collect mod files as PathBuf
     let mod_file_entries: Vec<PathBuf> = mod_files
@@ -141,9 +136,38 @@ pub fn discriminate(mod_path: String, game_path: String) -> Vec<String> {
     //Check if the files are .txt
     all_files.retain(|path| path.extension().map_or(false, |ext| ext == "txt"));
 
+    // The game loads the files sorted by name whether they come from the mod or not
+    // read_dir gives them in no particular order
+    all_files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+
     // Convert to Vec<String>
     all_files
         .iter()
         .filter_map(|path| path.to_str().map(|s| s.to_string()))
         .collect()
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_in_game_order() {
+        let root = std::env::temp_dir().join(format!("vic3_discriminate_{}", std::process::id()));
+        let (game, mod_root) = (root.join("game"), root.join("mod"));
+        fs::create_dir_all(&game).unwrap();
+        fs::create_dir_all(&mod_root).unwrap();
+        for name in ["20_industrial.txt", "00_goods.txt", "10_luxury.txt", "readme.md"] {
+            fs::write(game.join(name), "").unwrap();
+        }
+        for name in ["10_luxury.txt", "05_mod_goods.txt"] {
+            fs::write(mod_root.join(name), "").unwrap();
+        }
+
+        let files = discriminate(mod_root.to_string_lossy().into_owned(), game.to_string_lossy().into_owned());
+        fs::remove_dir_all(&root).unwrap();
+
+        // The mod file takes the place of the game file with the same name
+        let expected = [game.join("00_goods.txt"), mod_root.join("05_mod_goods.txt"), mod_root.join("10_luxury.txt"), game.join("20_industrial.txt")];
+        assert_eq!(files, expected.iter().map(|path| path.to_string_lossy().into_owned()).collect::<Vec<String>>());
+    }
+}
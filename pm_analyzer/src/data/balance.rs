@@ -0,0 +1,371 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Deserialize;
+
+use super::{pm::GoodsAmount, prices::market_price, Attribute, Data};
+
+// The simulation stops once no building changes its throughput by more than this
+const TOLERANCE: f32 = 1e-4;
+const MAX_ITERATIONS: usize = 1000;
+
+// What the simulated economy is made of, read from a YAML or JSON file
+// buildings:
+//   - building: building_steel_mills
+//     levels: 5
+//     pms: [pm_bessemer]
+// pops:
+//   5: 200000
+// demand:
+//   steel: 20
+#[derive(Debug, Deserialize)]
+pub struct Scenario {
+    buildings: Vec<ScenarioBuilding>,
+    // wealth -> number of pops, they buy the goods of their buy package
+    #[serde(default)]
+    pops: HashMap<u32, f32>,
+    // Bought on top of what the buildings and pops use, e.g. by the government
+    #[serde(default)]
+    demand: HashMap<String, f32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScenarioBuilding {
+    building: String,
+    #[serde(default = "one_level")]
+    levels: u32,
+    // Replace the default PM of their group, see Building::get_chosen_pms
+    #[serde(default)]
+    pms: Vec<String>,
+}
+
+fn one_level() -> u32 {
+    1
+}
+
+impl Scenario {
+    // .json files are read as JSON and everything else as YAML
+    pub fn read(path: &str) -> Result<Scenario, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let scenario: Scenario = if path.ends_with(".json") {
+            serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?
+        } else {
+            serde_yaml::from_str(&text).map_err(|e| format!("{}: {}", path, e))?
+        };
+        // Everything per level would be divided by 0
+        if let Some(building) = scenario.buildings.iter().find(|b| b.levels == 0) {
+            return Err(format!("{}: {} has 0 levels", path, building.building));
+        }
+        Ok(scenario)
+    }
+}
+
+// A building of the scenario once the simulation is over, every amount is for all of its levels
+#[derive(Debug)]
+pub struct BuildingBalance {
+    building: String,
+    levels: u32,
+    pms: Vec<String>,
+    // Part of its inputs the building gets, it only makes that part of its outputs
+    throughput: f32,
+    // good -> amount at full throughput
+    inputs: BTreeMap<String, f32>,
+    outputs: BTreeMap<String, f32>,
+    revenue: f32,
+    input_cost: f32,
+    // Relative wages, see Wages
+    wage_cost: f32,
+}
+
+impl BuildingBalance {
+    pub fn get_building(&self) -> &String {
+        &self.building
+    }
+
+    pub fn get_levels(&self) -> u32 {
+        self.levels
+    }
+
+    pub fn get_pms(&self) -> &Vec<String> {
+        &self.pms
+    }
+
+    pub fn get_throughput(&self) -> f32 {
+        self.throughput
+    }
+
+    pub fn get_revenue(&self) -> f32 {
+        self.revenue
+    }
+
+    pub fn get_input_cost(&self) -> f32 {
+        self.input_cost
+    }
+
+    pub fn get_wage_cost(&self) -> f32 {
+        self.wage_cost
+    }
+
+    // Before wages, they are only relative
+    pub fn get_profit(&self) -> f32 {
+        self.revenue - self.input_cost
+    }
+}
+
+// Supply, demand and price of a good once the simulation is over
+#[derive(Debug)]
+pub struct GoodBalance {
+    good: String,
+    supply: f32,
+    // Pops included
+    demand: f32,
+    pop_demand: f32,
+    price: f32,
+}
+
+impl GoodBalance {
+    pub fn get_good(&self) -> &String {
+        &self.good
+    }
+
+    pub fn get_supply(&self) -> f32 {
+        self.supply
+    }
+
+    pub fn get_demand(&self) -> f32 {
+        self.demand
+    }
+
+    pub fn get_pop_demand(&self) -> f32 {
+        self.pop_demand
+    }
+
+    pub fn get_price(&self) -> f32 {
+        self.price
+    }
+}
+
+// A single market where the buildings of the scenario sell their outputs and buy their inputs
+// Every round the prices follow the supply and demand with the formula of the game, then every building
+// moves towards the throughput its inputs and its profit allow: a building short of inputs only makes what they
+// are enough for and a building selling for less than its inputs cost makes less, which moves the prices again
+// The rounds go on until neither the throughputs nor the prices change anymore
+pub struct Balance {
+    buildings: Vec<BuildingBalance>,
+    goods: Vec<GoodBalance>,
+    iterations: usize,
+    converged: bool,
+}
+
+impl Balance {
+    pub fn simulate(data: &Data, scenario: &Scenario) -> Result<Balance, String> {
+        let mut buildings = Vec::new();
+        for entry in &scenario.buildings {
+            let building = data.get_building(&entry.building).ok_or(format!("Unknown building {}", entry.building))?;
+            let pms = building.get_chosen_pms(data, &entry.pms);
+            for pm in &entry.pms {
+                if !pms.contains(pm) {
+                    return Err(format!("{} can't use {}", entry.building, pm));
+                }
+            }
+            let efficiency = building.get_combination_data(data, &pms).ok_or(format!("Unknown PM in {}", entry.building))?;
+            let levels = entry.levels as f32;
+            let scaled = |amounts: &HashMap<String, GoodsAmount>| -> BTreeMap<String, f32> {
+                amounts.iter().map(|(good, a)| (good.clone(), a.total_for(levels))).filter(|(_, a)| *a > 0.).collect()
+            };
+            buildings.push(BuildingBalance {
+                building: entry.building.clone(),
+                levels: entry.levels,
+                pms,
+                throughput: 1.,
+                inputs: scaled(efficiency.get_inputs()),
+                outputs: scaled(efficiency.get_outputs()),
+                revenue: 0.,
+                input_cost: 0.,
+                wage_cost: efficiency.get(Attribute::WageCost) * levels,
+            });
+        }
+
+        // Pops buy the same whatever the prices
+        let pop_demand = data.get_pop_needs().demand_of(&scenario.pops);
+        let mut fixed_demand = scenario.demand.clone();
+        for (good, amount) in &pop_demand {
+            *fixed_demand.entry(good.clone()).or_insert(0.) += amount;
+        }
+
+        let mut iterations = 0;
+        let mut converged = false;
+        let mut prices: HashMap<String, f32> = HashMap::new();
+        while iterations < MAX_ITERATIONS && !converged {
+            iterations += 1;
+            let (supply, demand) = market(&buildings, &fixed_demand);
+            let new_prices = market_prices(data, &supply, &demand);
+            // Relative to the base cost, a cheap good moves by less
+            converged = new_prices.iter().all(|(good, price)| {
+                prices.get(good).is_some_and(|old| (price - old).abs() <= TOLERANCE * data.get_cost(good).max(1) as f32)
+            });
+            prices = new_prices;
+            for building in &mut buildings {
+                // How much more (or less) the building could make, from its inputs and from its profit
+                let supplied = building.inputs.keys()
+                    .map(|good| supply_ratio(supply.get(good), demand.get(good)))
+                    .fold(f32::INFINITY, f32::min);
+                let profitable = profit_ratio(building, &prices);
+                let growth = supplied.min(profitable);
+                let target = if growth.is_infinite() { 1. } else { (building.throughput * growth).min(1.) };
+                // Halfway there every time, jumping straight to it can swing back and forth forever
+                let throughput = (building.throughput + target) / 2.;
+                if (throughput - building.throughput).abs() > TOLERANCE {
+                    converged = false;
+                }
+                building.throughput = throughput;
+            }
+        }
+
+        // Prices and profits of the settled market
+        let (supply, demand) = market(&buildings, &fixed_demand);
+        let prices = market_prices(data, &supply, &demand);
+        let mut names: Vec<String> = data.get_goods_names();
+        names.extend(supply.keys().chain(demand.keys()).cloned());
+        names.sort();
+        names.dedup();
+        let goods: Vec<GoodBalance> = names.into_iter().map(|good| GoodBalance {
+            supply: *supply.get(&good).unwrap_or(&0.),
+            demand: *demand.get(&good).unwrap_or(&0.),
+            pop_demand: *pop_demand.get(&good).unwrap_or(&0.),
+            price: prices.get(&good).copied().unwrap_or(data.get_cost(&good) as f32),
+            good,
+        }).collect();
+        for building in &mut buildings {
+            building.revenue = value(&building.outputs, &prices) * building.throughput;
+            building.input_cost = value(&building.inputs, &prices) * building.throughput;
+        }
+        Ok(Balance { buildings, goods, iterations, converged })
+    }
+
+    pub fn get_buildings(&self) -> &Vec<BuildingBalance> {
+        &self.buildings
+    }
+
+    pub fn get_goods(&self) -> &Vec<GoodBalance> {
+        &self.goods
+    }
+
+    pub fn get_iterations(&self) -> usize {
+        self.iterations
+    }
+
+    pub fn is_converged(&self) -> bool {
+        self.converged
+    }
+}
+
+// (supply, demand) of every good at the current throughputs
+fn market(buildings: &[BuildingBalance], fixed_demand: &HashMap<String, f32>) -> (HashMap<String, f32>, HashMap<String, f32>) {
+    let mut supply: HashMap<String, f32> = HashMap::new();
+    let mut demand: HashMap<String, f32> = fixed_demand.clone();
+    for building in buildings {
+        for (good, amount) in &building.outputs {
+            *supply.entry(good.clone()).or_insert(0.) += amount * building.throughput;
+        }
+        for (good, amount) in &building.inputs {
+            *demand.entry(good.clone()).or_insert(0.) += amount * building.throughput;
+        }
+    }
+    (supply, demand)
+}
+
+// Price of every good that is sold or bought
+fn market_prices(data: &Data, supply: &HashMap<String, f32>, demand: &HashMap<String, f32>) -> HashMap<String, f32> {
+    supply.keys().chain(demand.keys())
+        .map(|good| (good.clone(), market_price(data.get_cost(good) as f32, *supply.get(good).unwrap_or(&0.), *demand.get(good).unwrap_or(&0.))))
+        .collect()
+}
+
+// Value of the goods at these prices, the goods nobody trades are worth nothing
+fn value(amounts: &BTreeMap<String, f32>, prices: &HashMap<String, f32>) -> f32 {
+    amounts.iter().map(|(good, a)| a * prices.get(good).unwrap_or(&0.)).sum()
+}
+
+// Supply over demand of a good, every buyer gets the same part of what it asks for
+// Infinite when nobody buys it
+fn supply_ratio(supply: Option<&f32>, demand: Option<&f32>) -> f32 {
+    let demand = *demand.unwrap_or(&0.);
+    if demand <= 0. {
+        return f32::INFINITY;
+    }
+    supply.unwrap_or(&0.) / demand
+}
+
+// Revenue over input cost of a building at these prices, infinite without inputs
+fn profit_ratio(building: &BuildingBalance, prices: &HashMap<String, f32>) -> f32 {
+    let input_cost = value(&building.inputs, prices);
+    if input_cost <= 0. {
+        return f32::INFINITY;
+    }
+    value(&building.outputs, prices) / input_cost
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chain_settles() {
+        let data = crate::data::tests::data_from(
+            "balance_chain",
+            "iron = { cost = 40 } steel = { cost = 50 }",
+            "building_iron_mine = { production_method_groups = { pmg_iron } } building_steel_mills = { production_method_groups = { pmg_steel } }",
+            "pmg_iron = { production_methods = { pm_iron } } pmg_steel = { production_methods = { pm_steel } }",
+            "pm_iron = { building_modifiers = { workforce_scaled = { goods_output_iron_add = 10 } } }
+            pm_steel = { building_modifiers = { workforce_scaled = { goods_input_iron_add = 10 goods_output_steel_add = 5 } } }",
+        );
+        let building = |name: &str, levels: u32| ScenarioBuilding { building: name.to_string(), levels, pms: Vec::new() };
+        // The mills want twice the iron of the mine, they can only run at half and sell half the steel that is bought
+        let scenario = Scenario {
+            buildings: vec![building("building_iron_mine", 1), building("building_steel_mills", 2)],
+            pops: HashMap::new(),
+            demand: HashMap::from([("steel".to_string(), 10.)]),
+        };
+        let balance = Balance::simulate(&data, &scenario).unwrap();
+        assert!(balance.is_converged());
+        let throughputs: Vec<f32> = balance.get_buildings().iter().map(|b| b.get_throughput()).collect();
+        assert_eq!(throughputs[0], 1.);
+        assert!((throughputs[1] - 0.5).abs() < 1e-3);
+        let price = |good: &str| balance.get_goods().iter().find(|g| g.get_good() == good).unwrap().get_price();
+        assert!((price("iron") - 40.).abs() < 0.1);
+        // 5 steel for 10 bought
+        assert!((price("steel") - 87.5).abs() < 0.1);
+        assert!(balance.get_buildings()[1].get_profit() > 0.);
+    }
+
+    #[test]
+    fn unprofitable_building_shrinks() {
+        // The iron costs more than the steel sells for even at the highest price, the mills stop and the iron is left over
+        let data = crate::data::tests::data_from(
+            "balance_loss",
+            "iron = { cost = 40 } steel = { cost = 10 }",
+            "building_iron_mine = { production_method_groups = { pmg_iron } } building_steel_mills = { production_method_groups = { pmg_steel } }",
+            "pmg_iron = { production_methods = { pm_iron } } pmg_steel = { production_methods = { pm_steel } }",
+            "pm_iron = { building_modifiers = { workforce_scaled = { goods_output_iron_add = 10 } } }
+            pm_steel = { building_modifiers = { workforce_scaled = { goods_input_iron_add = 10 goods_output_steel_add = 1 } } }",
+        );
+        let building = |name: &str| ScenarioBuilding { building: name.to_string(), levels: 1, pms: Vec::new() };
+        let scenario = Scenario {
+            buildings: vec![building("building_iron_mine"), building("building_steel_mills")],
+            pops: HashMap::new(),
+            demand: HashMap::from([("steel".to_string(), 1.)]),
+        };
+        let balance = Balance::simulate(&data, &scenario).unwrap();
+        assert!(balance.is_converged());
+        assert!(balance.get_buildings()[1].get_throughput() < 1e-3);
+    }
+
+    #[test]
+    fn zero_levels_rejected() {
+        let path = std::env::temp_dir().join(format!("vic3_scenario_{}.yaml", std::process::id()));
+        std::fs::write(&path, "buildings:\n  - building: building_steel_mills\n    levels: 0\n  - building: building_iron_mine\n").unwrap();
+        let result = Scenario::read(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.unwrap_err().ends_with("building_steel_mills has 0 levels"));
+    }
+}
@@ -1,14 +1,21 @@
 use std::collections::HashMap;
 
-use crate::Tree;
+use vic3_game_data::Building as BuildingDefinition;
 
-use super::{Attribute::{self, *}, Data};
+use super::{pm::{GoodsAmount, PM}, Attribute::{self, *}, Data};
 
 #[derive(Debug)]
 pub struct Building {
     name: String,
     pmgs: Vec<String>,
     cost: f32,
+    // Technologies needed before the building can be built
+    unlocking_technologies: Vec<String>,
+    building_group: String,
+    buildable: bool,
+    expandable: bool,
+    // Capped by the resources of the state
+    has_max_level: bool,
 }
 
 #[derive(Debug)]
@@ -17,57 +24,91 @@ pub struct EfficiencyData {
     input: f32,
     output: f32,
     labor: f32,
-    cost: f32
+    cost: f32,
+    // good -> amounts of all the PMs of the building
+    inputs: HashMap<String, GoodsAmount>,
+    outputs: HashMap<String, GoodsAmount>,
+    // profession -> workers per level
+    employment: HashMap<String, f32>,
+    wage_cost: f32,
 }
 
 impl Building {
-    pub fn from_tree(tree: &Tree, building_values: &HashMap<String, f32>) -> Result<Self, String> {
-        let name = tree.get_name();
-        let pmgs = tree.get("production_method_groups")?.get_children_names();
-        let value =  { 
-            if let Ok(constr_val) = tree.get("required_construction") {
-                constr_val.value().unwrap_or("0.0".to_owned())
-            } else {
-                "0.0".to_owned()
-            }
-        };
+    pub fn from_definition(name: &str, definition: &BuildingDefinition, script_values: &HashMap<String, f32>) -> Self {
+        let pmgs = definition.get_production_method_groups().clone();
+        // Either a number or a script value from building_values.txt
+        let value = definition.get_required_construction().cloned().unwrap_or("0.0".to_owned());
         let cost = match value.parse::<f32>() {
             Ok(cost) => {
                 cost
             }
             Err(_) => {
-                *building_values.get(&value).unwrap_or(&0.)
+                *script_values.get(&value).unwrap_or(&0.)
             }
         };
-        Ok(Building { name, pmgs, cost } )
+        Building {
+            name: name.to_string(),
+            pmgs,
+            cost,
+            unlocking_technologies: definition.get_unlocking_technologies().clone(),
+            building_group: definition.get_building_group().to_string(),
+            buildable: definition.is_buildable(),
+            expandable: definition.is_expandable(),
+            has_max_level: definition.has_max_level(),
+        }
     }
 
     pub fn name(&self) -> &String {
         &self.name
     }
 
+    pub fn get_unlocking_technologies(&self) -> &Vec<String> {
+        &self.unlocking_technologies
+    }
+
+    pub fn get_building_group(&self) -> &String {
+        &self.building_group
+    }
+
+    pub fn is_buildable(&self) -> bool {
+        self.buildable
+    }
+
+    pub fn is_expandable(&self) -> bool {
+        self.expandable
+    }
+
+    pub fn is_subsistence(&self, data: &Data) -> bool {
+        data.get_building_groups().is_subsistence(&self.building_group)
+    }
+
+    // Mines, logging camps and the like, how many levels a state can have depends on its resources
+    pub fn needs_resources(&self) -> bool {
+        self.has_max_level
+    }
+
+    // Farms, plantations and the like, their group or one of its parents is in the arable_resources of some state
+    pub fn needs_arable_land(&self, data: &Data) -> bool {
+        !self.has_max_level && data.get_building_groups().get_path(&self.building_group).iter().any(|group| data.is_arable_group(group))
+    }
+
     pub fn get_default_data(&self, data: &Data) -> Option<EfficiencyData> {
-        let mut input = 0.;
-        let mut output = 0.;
-        let mut labor = 0.;
+        let mut result = EfficiencyData::new(self.name().clone(), self.cost);
         for pmg in &self.pmgs {
             if let Some(pmg) = data.get_pmg(pmg) {
                 if let Some(pm) = pmg.get_default(data) {
                     if let Some(pm) = data.get_pm(&pm) {
-                        input += pm.get(Input);
-                        output += pm.get(Output);
-                        labor += pm.get(Labor);
+                        result.add_pm(pm);
                     }
                 }
             }
         }
-        Some(EfficiencyData { name: self.name().clone(), input, output, labor: labor.max(0.), cost: self.cost })
+        result.finish(data);
+        Some(result)
     }
 
     pub fn get_pm_data(&self, data: &Data, pm_name: &str) -> Option<EfficiencyData> {
-        let mut input = 0.;
-        let mut output = 0.;
-        let mut labor = 0.;
+        let mut result = EfficiencyData::new(self.name().clone(), self.cost);
         data.get_pm(pm_name)?;
         for pmg in &self.pmgs {
             if let Some(pmg) = data.get_pmg(pmg) {
@@ -78,13 +119,37 @@ impl Building {
                     pm = pmg.get_default(data)?;
                 }
                 if let Some(pm) = data.get_pm(&pm) {
-                    input += pm.get(Input);
-                    output += pm.get(Output);
-                    labor += pm.get(Labor);
+                    result.add_pm(pm);
                 }
             }
         }
-        Some(EfficiencyData { name: self.name().clone(), input, output, labor: labor.max(0.) , cost: self.cost })
+        result.finish(data);
+        Some(result)
+    }
+
+    // Every way to pick one PM in each group, e.g. 3 base PMs and 2 automation PMs give 6 combinations
+    pub fn get_combinations(&self, data: &Data) -> Vec<Vec<String>> {
+        let mut combinations: Vec<Vec<String>> = vec![Vec::new()];
+        for (_, pms) in self.get_pm_by_pmgs(data).into_iter().filter(|(_, pms)| !pms.is_empty()) {
+            combinations = combinations.into_iter().flat_map(|combination| {
+                pms.iter().map(move |pm| {
+                    let mut combination = combination.clone();
+                    combination.push(pm.clone());
+                    combination
+                })
+            }).collect();
+        }
+        combinations
+    }
+
+    // The building running exactly these PMs, one for each group
+    pub fn get_combination_data(&self, data: &Data, pms: &[String]) -> Option<EfficiencyData> {
+        let mut result = EfficiencyData::new(self.name().clone(), self.cost);
+        for pm in pms {
+            result.add_pm(data.get_pm(pm)?);
+        }
+        result.finish(data);
+        Some(result)
     }
 
     pub fn get_pm_names(&self, data: &Data) -> Vec<String> {
@@ -99,9 +164,74 @@ impl Building {
             Some((pmg.name().clone(), pmg.get_pms()))
         }).collect()
     }
+
+    // One PM for each group, the chosen one when the group has it and the default one otherwise
+    pub fn get_chosen_pms(&self, data: &Data, chosen: &[String]) -> Vec<String> {
+        self.pmgs.iter().filter_map(|pmg| {
+            let pmg = data.get_pmg(pmg)?;
+            let pms = pmg.get_pms();
+            pms.iter().find(|pm| chosen.contains(pm)).cloned().or_else(|| pmg.get_default(data))
+        }).collect()
+    }
 }
 
 impl EfficiencyData {
+    fn new(name: String, cost: f32) -> EfficiencyData {
+        EfficiencyData { name, input: 0., output: 0., labor: 0., cost, inputs: HashMap::new(), outputs: HashMap::new(), employment: HashMap::new(), wage_cost: 0. }
+    }
+
+    // Called once every PM of the building has been added
+    // The multipliers apply to the total of the building, not to the PM that has them
+    // so the value of the goods is only known once every PM is there
+    fn finish(&mut self, data: &Data) {
+        self.input = data.get_goods().value_of(&self.inputs);
+        self.output = data.get_goods().value_of(&self.outputs);
+        self.labor = self.labor.max(0.);
+        self.wage_cost = data.get_wages().cost(&self.employment);
+    }
+
+    fn add_pm(&mut self, pm: &PM) {
+        self.labor += pm.get(Labor).unwrap_or(0.);
+        for (good, amount) in pm.get_inputs() {
+            self.inputs.entry(good.clone()).or_default().combine(amount);
+        }
+        for (good, amount) in pm.get_outputs() {
+            self.outputs.entry(good.clone()).or_default().combine(amount);
+        }
+        for (profession, amount) in pm.get_employment() {
+            *self.employment.entry(profession.clone()).or_insert(0.) += amount;
+        }
+    }
+
+    pub fn get_employment(&self) -> &HashMap<String, f32> {
+        &self.employment
+    }
+
+    pub fn get_inputs(&self) -> &HashMap<String, GoodsAmount> {
+        &self.inputs
+    }
+
+    pub fn get_outputs(&self) -> &HashMap<String, GoodsAmount> {
+        &self.outputs
+    }
+
+    // e.g. 30 iron + 15 coal -> 60 steel (+10%), with the goods named by localize
+    pub fn goods_summary(&self, localize: impl Fn(&str) -> String) -> String {
+        let side = |amounts: &HashMap<String, GoodsAmount>| {
+            let mut goods: Vec<(&String, &GoodsAmount)> = amounts.iter().filter(|(_, a)| a.get_add() != 0. || a.get_unscaled() != 0. || a.get_mult() != 0.).collect();
+            goods.sort_by(|a, b| a.0.cmp(b.0));
+            goods.iter().map(|(good, amount)| {
+                // A single level, the unscaled amount included
+                let mut text = format!("{} {}", amount.get_add() + amount.get_unscaled(), localize(good));
+                if amount.get_mult() != 0. {
+                    text.push_str(&format!(" ({:+}%)", amount.get_mult() * 100.));
+                }
+                text
+            }).collect::<Vec<String>>().join(" + ")
+        };
+        format!("{} -> {}", side(&self.inputs), side(&self.outputs))
+    }
+
     pub fn get(&self, attribute: Attribute) -> f32 {
         match attribute {
             Input => self.input,
@@ -111,9 +241,27 @@ impl EfficiencyData {
             EfficiencyPerWorker => (self.output - self.input) / self.labor,
             NetOutput => self.output - self.input,
             EfficiencyPerConstruction => (self.output - self.input) / self.cost,
+            WageCost => self.wage_cost,
+            // NaN without wages, try_get gives None instead
+            ProfitPerWage => self.profit_per_wage().unwrap_or(f32::NAN),
+        }
+    }
+
+    // None when the attribute means nothing for this building, e.g. profit per wage without paid workers
+    pub fn try_get(&self, attribute: Attribute) -> Option<f32> {
+        match attribute {
+            ProfitPerWage => self.profit_per_wage(),
+            attribute => Some(self.get(attribute)),
         }
     }
 
+    fn profit_per_wage(&self) -> Option<f32> {
+        if self.wage_cost <= 0. {
+            return None;
+        }
+        Some((self.output - self.input) / self.wage_cost)
+    }
+
     pub fn name(&self) -> &String {
         &self.name
     }
@@ -123,4 +271,26 @@ impl ToString for EfficiencyData {
     fn to_string(&self) -> String {
         format!("{}: Net Value: {:.2}, Efficiency per ten construction: {:.2}, Efficiency per hundred workers: {:.2}", self.name, self.get(NetOutput), self.get(EfficiencyPerConstruction) * 10.0, self.get(EfficiencyPerWorker) * 100.0)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::tests::data_from;
+
+    #[test]
+    fn one_pm_per_group() {
+        let data = data_from(
+            "combinations",
+            "iron = { cost = 40 }",
+            "building_iron_mine = { production_method_groups = { pmg_base pmg_automation pmg_empty pmg_unknown } }",
+            "pmg_base = { production_methods = { pm_a pm_b pm_c } } pmg_automation = { production_methods = { pm_x pm_y } }
+            pmg_empty = { production_methods = { } }",
+            "pm_a = { is_default = yes } pm_b = { } pm_c = { } pm_x = { is_default = yes } pm_y = { }",
+        );
+        let mut combinations = data.get_building("building_iron_mine").unwrap().get_combinations(&data);
+        combinations.sort();
+        assert_eq!(combinations.len(), 6);
+        assert_eq!(combinations[0], vec!["pm_a", "pm_x"]);
+        assert_eq!(combinations[5], vec!["pm_c", "pm_y"]);
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use vic3_game_data::{BuildingGroup, Database};
+
+// A building group with what it takes from its parents
+#[derive(Debug)]
+struct GroupInfo {
+    // The group first and the top group last, e.g. bg_coal_mining, bg_mining, bg_resource
+    path: Vec<String>,
+    category: Option<String>,
+    land_usage: Option<String>,
+    is_subsistence: bool,
+}
+
+// The hierarchy of common/building_groups
+pub struct BuildingGroups {
+    groups: HashMap<String, GroupInfo>,
+}
+
+impl BuildingGroups {
+    pub fn from_database(database: &Database<BuildingGroup>) -> BuildingGroups {
+        let mut groups = HashMap::new();
+        for name in database.get_names() {
+            let mut path = vec![name.clone()];
+            let mut current = database.get(name);
+            let (mut category, mut land_usage, mut is_subsistence) = (None, None, None);
+            while let Some(group) = current {
+                category = category.or(group.get_category().cloned());
+                land_usage = land_usage.or(group.get_land_usage().cloned());
+                is_subsistence = is_subsistence.or(group.is_subsistence());
+                // A group that is its own ancestor would never end
+                current = match group.get_parent_group() {
+                    Some(parent) if !path.contains(parent) => {
+                        path.push(parent.clone());
+                        database.get(parent)
+                    }
+                    _ => None,
+                };
+            }
+            groups.insert(name.clone(), GroupInfo { path, category, land_usage, is_subsistence: is_subsistence.unwrap_or(false) });
+        }
+        BuildingGroups { groups }
+    }
+
+    // The group and its parents, only the group itself when it is not in the files
+    pub fn get_path(&self, group: &str) -> Vec<String> {
+        self.groups.get(group).map(|g| g.path.clone()).unwrap_or(vec![group.to_string()])
+    }
+
+    // Whether the group is the ancestor or one of its children, at any depth
+    pub fn is_in(&self, group: &str, ancestor: &str) -> bool {
+        self.get_path(group).iter().any(|g| g == ancestor)
+    }
+
+    pub fn get_category(&self, group: &str) -> Option<&String> {
+        self.groups.get(group)?.category.as_ref()
+    }
+
+    pub fn get_land_usage(&self, group: &str) -> Option<&String> {
+        self.groups.get(group)?.land_usage.as_ref()
+    }
+
+    pub fn is_subsistence(&self, group: &str) -> bool {
+        self.groups.get(group).is_some_and(|g| g.is_subsistence)
+    }
+}
@@ -0,0 +1,41 @@
+use vic3_game_data::EffectBlock;
+
+// What the history of a country sets up at the start of the game
+#[derive(Debug)]
+pub struct CountrySetup {
+    tag: String,
+    // activate_law = law:law_x, also the ones of the scripted effects the history calls
+    laws: Vec<String>,
+    // add_technology_researched = x, without their prerequisites
+    technologies: Vec<String>,
+    // Where the country owns land at the start, from common/history/states
+    states: Vec<String>,
+}
+
+impl CountrySetup {
+    // history has its scripted effects followed, see GameData::get_country_setup
+    pub fn new(tag: &str, history: &EffectBlock, states: Vec<String>) -> CountrySetup {
+        CountrySetup {
+            tag: tag.to_string(),
+            laws: history.get_laws().clone(),
+            technologies: history.get_technologies().clone(),
+            states,
+        }
+    }
+
+    pub fn get_tag(&self) -> &String {
+        &self.tag
+    }
+
+    pub fn get_laws(&self) -> &Vec<String> {
+        &self.laws
+    }
+
+    pub fn get_technologies(&self) -> &Vec<String> {
+        &self.technologies
+    }
+
+    pub fn get_states(&self) -> &Vec<String> {
+        &self.states
+    }
+}
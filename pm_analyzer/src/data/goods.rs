@@ -1,14 +1,21 @@
 use std::collections::HashMap;
-use vic3_parser::Tree;
+use vic3_game_data::{Database, Good};
+
+use super::{pm::GoodsAmount, prices::PriceScenario};
 
 pub struct Goods {
     cost: HashMap<String, i32>,
+    // In the order of the files, the save refers to goods by their position
+    order: Vec<String>,
+    scenario: PriceScenario,
 }
 
 impl Goods {
     pub fn new() -> Goods {
         Goods {
             cost: HashMap::new(),
+            order: Vec::new(),
+            scenario: PriceScenario::base(),
         }
     }
 
@@ -16,24 +23,44 @@ impl Goods {
         *self.cost.get(key).unwrap_or(&0)
     }
 
+    // Price of the good in the current scenario, the base cost unless set otherwise
+    pub fn get_price(&self, key: &str) -> f32 {
+        self.scenario.price(key, self.get_cost(key) as f32)
+    }
+
+    pub fn get_scenario(&self) -> &PriceScenario {
+        &self.scenario
+    }
+
+    pub fn set_scenario(&mut self, scenario: PriceScenario) {
+        self.scenario = scenario;
+    }
+
+    // Value of a list of goods at the current prices, with their multipliers applied
+    pub fn value_of(&self, amounts: &HashMap<String, GoodsAmount>) -> f32 {
+        amounts.iter().map(|(good, amount)| self.get_price(good) * amount.total()).sum()
+    }
+
+    pub fn get_order(&self) -> &Vec<String> {
+        &self.order
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.cost.keys().cloned().collect()
+    }
+
     fn set_cost(&mut self, key: &str, value: i32) {
         self.cost.insert(key.to_string(), value);
     }
 }
 
 impl Goods {
-    pub fn from_tree(tree: Tree) -> Result<Self, String> {
+    pub fn from_database(database: &Database<Good>) -> Self {
         let mut goods = Goods::new();
-        for child in tree.get_children_names() {
-            let value = tree.get(child.clone())?.get("cost")?.value()?.parse::<i32>().unwrap();
-            goods.set_cost(&child, value);
+        for (name, definition) in database.iter() {
+            goods.set_cost(name, definition.get_cost());
         }
-        Ok(goods)
+        goods.order = database.get_names().clone();
+        goods
     }
 }
-
-impl From<Tree> for Goods {
-    fn from(tree: Tree) -> Self {
-        Goods::from_tree(tree).unwrap()
-    }
-}
\ No newline at end of file
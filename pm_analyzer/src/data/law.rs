@@ -0,0 +1,39 @@
+use std::collections::{HashMap, HashSet};
+
+use vic3_game_data::{Database, Law};
+
+// Every law and the group it belongs to
+pub struct Laws {
+    // law -> group
+    groups: HashMap<String, String>,
+    // group -> law a country has when nothing else is set, the first law of the group like in the country editor
+    defaults: HashMap<String, String>,
+}
+
+impl Laws {
+    pub fn from_database(database: &Database<Law>) -> Laws {
+        let mut groups = HashMap::new();
+        let mut defaults = HashMap::new();
+        for (name, law) in database.iter() {
+            defaults.entry(law.get_group().to_string()).or_insert(name.clone());
+            groups.insert(name.clone(), law.get_group().to_string());
+        }
+        Laws { groups, defaults }
+    }
+
+    pub fn get_group(&self, law: &str) -> Option<&String> {
+        self.groups.get(law)
+    }
+
+    // The law of every group once these laws are enacted, the other groups keep their default law
+    pub fn active_laws(&self, enacted: &[String]) -> HashSet<String> {
+        let mut active = self.defaults.clone();
+        for law in enacted {
+            match self.groups.get(law) {
+                Some(group) => { active.insert(group.clone(), law.clone()); }
+                None => { println!("Unknown law {}", law) }
+            }
+        }
+        active.into_values().collect()
+    }
+}
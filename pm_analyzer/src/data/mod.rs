@@ -1,18 +1,40 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 
 mod pm;
 mod goods;
 mod pmg;
 mod building;
+mod building_group;
+mod wages;
+mod prices;
+mod law;
+mod country;
+mod supply_chain;
+mod true_cost;
+mod balance;
+mod pop_needs;
+mod state;
 
 use building::Building;
+pub use building_group::BuildingGroups;
 use goods::Goods;
 use pm::PM;
 use pmg::PMG;
+pub use wages::Wages;
+pub use law::Laws;
+pub use country::CountrySetup;
+pub use supply_chain::SupplyChain;
+pub use true_cost::{TrueCost, PRICE_OUTLIER_FACTOR};
+pub use balance::{Balance, Scenario};
+pub use pop_needs::{PopNeeds, PACKAGE_POPULATION};
+pub use state::state_buildings;
+pub use prices::{PriceScenario, MAX_PRICE_FACTOR, MIN_PRICE_FACTOR};
 
-use vic3_parser::Tree;
+use vic3_game_data::{Database, GameData, StateRegion, Technologies};
+use vic3_parser::localization::Localization;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Attribute {
     Input,
     Output,
@@ -20,7 +42,39 @@ pub enum Attribute {
     Labor,
     EfficiencyPerWorker,
     NetOutput,
-    EfficiencyPerConstruction
+    EfficiencyPerConstruction,
+    // Wage bill of the workers, see Wages
+    WageCost,
+    // Net output per unit of wage bill
+    ProfitPerWage
+}
+
+impl Attribute {
+    pub const ALL: [Attribute; 9] = [
+        Attribute::Input,
+        Attribute::Output,
+        Attribute::Construction,
+        Attribute::Labor,
+        Attribute::EfficiencyPerWorker,
+        Attribute::NetOutput,
+        Attribute::EfficiencyPerConstruction,
+        Attribute::WageCost,
+        Attribute::ProfitPerWage,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Attribute::Input => "Input",
+            Attribute::Output => "Output",
+            Attribute::Construction => "Construction",
+            Attribute::Labor => "Labor",
+            Attribute::EfficiencyPerWorker => "EfficiencyPerWorker",
+            Attribute::NetOutput => "NetOutput",
+            Attribute::EfficiencyPerConstruction => "EfficiencyPerConstruction",
+            Attribute::WageCost => "WageCost",
+            Attribute::ProfitPerWage => "ProfitPerWage",
+        }
+    }
 }
 
 
@@ -29,45 +83,48 @@ pub struct Data {
     pmgs: HashMap<String, PMG>,
     buildings: HashMap<String, Building>,
     goods: Goods,
+    localization: Localization,
+    wages: Wages,
+    technologies: Technologies,
+    // The country given with --country
+    country: Option<CountrySetup>,
+    pop_needs: PopNeeds,
+    building_groups: BuildingGroups,
+    state_regions: Database<StateRegion>,
+    // Every building group in the arable_resources of some state
+    arable_groups: HashSet<String>,
 }
 
 impl Data {
-    pub fn new(pms_tree: Tree, goods_tree: Tree, pmgs_tree: Tree, buildings_tree: Tree, building_values_tree: Tree) -> Result<Data, String> {
+    pub fn new(game: &GameData, localization: Localization, wages: Wages) -> Result<Data, String> {
 
         let mut pms = HashMap::new();
         let mut pmgs = HashMap::new();
         let mut buildings = HashMap::new();
 
-        let goods = Goods::from_tree(goods_tree)?;
+        let goods = Goods::from_database(game.get_goods());
+        print_errors(game.get_goods().get_errors());
 
-        for child in pms_tree {
-            match PM::from_tree(&child, &goods) {
-                Ok(pm) => { pms.insert(pm.name().clone(), pm); },
-                Err(e) => { println!("Error parsing {}: {}", child.get_name(), e) }
-            }
+        for (name, definition) in game.get_production_methods().iter() {
+            pms.insert(name.clone(), PM::from_definition(name, definition, &goods));
         }
-        for child in pmgs_tree {
-            match PMG::from_tree(&child) {
-                Ok(pmg) => { pmgs.insert(pmg.name().clone(), pmg); },
-                Err(e) => { println!("Error parsing {}: {}", child.get_name(), e) }
-            }
+        print_errors(game.get_production_methods().get_errors());
+        for (name, definition) in game.get_production_method_groups().iter() {
+            pmgs.insert(name.clone(), PMG::from_definition(name, definition));
         }
-        let building_values = Data::get_building_values(&building_values_tree);
-        for child in buildings_tree {
-            match Building::from_tree(&child, &building_values) {
-                Ok(building) => { buildings.insert(building.name().clone(), building); },
-                Err(e) => { println!("Error parsing {}: {}", child.get_name(), e) }
-            }
+        print_errors(game.get_production_method_groups().get_errors());
+        for (name, definition) in game.get_buildings().iter() {
+            buildings.insert(name.clone(), Building::from_definition(name, definition, game.get_script_values()));
         }
-        Ok(Data { pms, goods, pmgs, buildings } )
-    }
-
-    fn get_building_values(tree: &Tree) -> HashMap<String, f32> {
-        let mut values = HashMap::new();
-        for child in tree.clone() {
-            values.insert(child.get_name().clone(), child.value().unwrap().parse::<f32>().unwrap());
-        }
-        values
+        print_errors(game.get_buildings().get_errors());
+        let building_groups = BuildingGroups::from_database(game.get_building_groups());
+        print_errors(game.get_building_groups().get_errors());
+        print_errors(game.get_state_regions().get_errors());
+        let arable_groups = game.get_state_regions().iter().flat_map(|(_, region)| region.get_arable_resources().iter().cloned()).collect();
+        let pop_needs = PopNeeds::from_databases(game.get_pop_needs(), game.get_buy_packages());
+        print_errors(game.get_pop_needs().get_errors());
+        print_errors(game.get_buy_packages().get_errors());
+        Ok(Data { pms, goods, pmgs, buildings, localization, wages, technologies: game.get_technologies().clone(), country: None, pop_needs, building_groups, state_regions: game.get_state_regions().clone(), arable_groups } )
     }
 
     pub fn get_pm(&self, name: &str) -> Option<&PM> {
@@ -86,7 +143,156 @@ impl Data {
         self.buildings.values().collect()
     }
 
+    // Buildings with the PM in one of their groups
+    pub fn get_buildings_using(&self, pm: &str) -> Vec<&Building> {
+        self.buildings.values().filter(|building| building.get_pm_names(self).iter().any(|name| name == pm)).collect()
+    }
+
+    pub fn get_all_pms(&self) -> Vec<&PM> {
+        self.pms.values().collect()
+    }
+
+    pub fn get_all_pmgs(&self) -> Vec<&PMG> {
+        self.pmgs.values().collect()
+    }
+
+    // Every key that should have a localization entry, as (category, key) pairs
+    pub fn get_localization_keys(&self) -> Vec<(String, String)> {
+        let mut keys = Vec::new();
+        keys.extend(self.buildings.keys().map(|key| ("building".to_string(), key.clone())));
+        keys.extend(self.pms.keys().map(|key| ("production_method".to_string(), key.clone())));
+        keys.extend(self.pmgs.keys().map(|key| ("production_method_group".to_string(), key.clone())));
+        keys.extend(self.goods.names().into_iter().map(|key| ("goods".to_string(), key)));
+        keys
+    }
+
     pub fn get_cost(&self, key: &str) -> i32 {
         self.goods.get_cost(key)
     }
-}
\ No newline at end of file
+
+    pub fn get_goods(&self) -> &Goods {
+        &self.goods
+    }
+
+    // Every PM and EfficiencyData is valued at the prices of the scenario from now on
+    pub fn set_price_scenario(&mut self, scenario: PriceScenario) {
+        self.goods.set_scenario(scenario);
+        for pm in self.pms.values_mut() {
+            pm.revalue(&self.goods);
+        }
+    }
+
+    // Same as set_price_scenario but only these PMs are valued again, the other ones keep the prices they had
+    // EfficiencyData always uses the prices of the scenario
+    pub fn set_price_scenario_for(&mut self, scenario: PriceScenario, pms: &[String]) {
+        self.goods.set_scenario(scenario);
+        for pm in pms {
+            if let Some(pm) = self.pms.get_mut(pm) {
+                pm.revalue(&self.goods);
+            }
+        }
+    }
+
+    pub fn get_technologies(&self) -> &Technologies {
+        &self.technologies
+    }
+
+    pub fn get_country(&self) -> Option<&CountrySetup> {
+        self.country.as_ref()
+    }
+
+    pub fn set_country(&mut self, country: CountrySetup) {
+        self.country = Some(country);
+    }
+
+    // Drops the buildings and PMs that need a technology that is not in the set
+    // Every report only sees what is left
+    pub fn restrict_to_technologies(&mut self, unlocked: &HashSet<String>) {
+        let is_unlocked = |technologies: &Vec<String>| technologies.iter().all(|t| unlocked.contains(t));
+        self.pms.retain(|_, pm| is_unlocked(pm.get_unlocking_technologies()));
+        self.buildings.retain(|_, building| is_unlocked(building.get_unlocking_technologies()));
+        self.retain_pmg_pms();
+    }
+
+    // Drops the PMs that the unlocking_laws and disallowing_laws don't allow with these laws
+    pub fn restrict_to_laws(&mut self, laws: &HashSet<String>) {
+        self.pms.retain(|_, pm| pm.allowed_by_laws(laws));
+        self.retain_pmg_pms();
+    }
+
+    // The groups only keep the PMs that are still there
+    fn retain_pmg_pms(&mut self) {
+        let pms = &self.pms;
+        for pmg in self.pmgs.values_mut() {
+            pmg.retain_pms(|pm| pms.contains_key(pm));
+        }
+    }
+
+    pub fn get_building_groups(&self) -> &BuildingGroups {
+        &self.building_groups
+    }
+
+    // Drops the buildings that are not in one of these building groups or their children
+    pub fn restrict_to_groups(&mut self, groups: &[String]) {
+        let building_groups = &self.building_groups;
+        self.buildings.retain(|_, building| groups.iter().any(|group| building_groups.is_in(building.get_building_group(), group)));
+    }
+
+    // STATE_SVEALAND, SVEALAND and s:STATE_SVEALAND are the same state
+    pub fn get_state_region(&self, name: &str) -> Option<&StateRegion> {
+        let name = name.trim_start_matches("s:");
+        self.state_regions.get(name).or_else(|| self.state_regions.get(&format!("STATE_{}", name)))
+    }
+
+    // Whether some state has arable land for the group
+    pub fn is_arable_group(&self, group: &str) -> bool {
+        self.arable_groups.contains(group)
+    }
+
+    pub fn get_pop_needs(&self) -> &PopNeeds {
+        &self.pop_needs
+    }
+
+    pub fn get_wages(&self) -> &Wages {
+        &self.wages
+    }
+
+    pub fn get_goods_names(&self) -> Vec<String> {
+        self.goods.names()
+    }
+
+    // Display name of a building, PM or good in the selected language
+    // Falls back to the key when the localization is missing
+    pub fn localize(&self, key: &str) -> String {
+        self.localization.localize(key)
+    }
+}
+
+fn print_errors(errors: &[(String, String)]) {
+    for (name, e) in errors {
+        println!("Error parsing {}: {}", name, e);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use vic3_game_data::{BUILDINGS_FOLDER, GOODS_FOLDER, PRODUCTION_METHODS_FOLDER, PRODUCTION_METHOD_GROUPS_FOLDER};
+
+    // Goods, buildings, PMGs and PMs written as game files to a temporary folder and read back
+    // name keeps the folders of tests running at the same time apart
+    pub fn data_from(name: &str, goods: &str, buildings: &str, pmgs: &str, pms: &str) -> Data {
+        let root = std::env::temp_dir().join(format!("vic3_data_{}_{}", name, std::process::id()));
+        let game_path = root.join("game").to_str().unwrap().to_string();
+        for (folder, text) in [(GOODS_FOLDER, goods), (BUILDINGS_FOLDER, buildings), (PRODUCTION_METHOD_GROUPS_FOLDER, pmgs), (PRODUCTION_METHODS_FOLDER, pms)] {
+            // The folders are joined like the game paths, with backslashes
+            let folder = format!("{}{}", game_path, folder);
+            std::fs::create_dir_all(&folder).unwrap();
+            std::fs::write(std::path::Path::new(&folder).join("test.txt"), text).unwrap();
+        }
+        let game = GameData::new(root.join("mod").to_str().unwrap(), game_path);
+        let data = Data::new(&game, Localization::new("english"), Wages::default());
+        std::fs::remove_dir_all(&root).unwrap();
+        data.unwrap()
+    }
+}
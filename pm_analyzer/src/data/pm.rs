@@ -1,7 +1,9 @@
+use std::collections::{HashMap, HashSet};
+
 use super::goods::Goods;
-use vic3_parser::Tree;
+use vic3_game_data::ProductionMethod;
 
-use super::Attribute;
+use super::{Attribute, Data};
 
 #[derive(Debug)]
 pub struct PM {
@@ -10,41 +12,113 @@ pub struct PM {
     output: f32,
     labor: f32,
     is_default: bool,
+    // good -> amount, from goods_input_<good>_add/_mult and goods_output_<good>_add/_mult
+    inputs: HashMap<String, GoodsAmount>,
+    outputs: HashMap<String, GoodsAmount>,
+    // profession -> workers per level
+    employment: HashMap<String, f32>,
+    // Technologies needed before the PM can be used
+    unlocking_technologies: Vec<String>,
+    // One of these laws has to be enacted, when there are any
+    unlocking_laws: Vec<String>,
+    // None of these laws can be enacted
+    disallowing_laws: Vec<String>,
+}
+
+// What a PM does to a single good
+// add is a flat amount per level, unscaled a flat amount for the whole building
+// and mult multiplies the total amount of the building (0.1 is +10%)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GoodsAmount {
+    add: f32,
+    unscaled: f32,
+    mult: f32,
+}
+
+impl GoodsAmount {
+    pub fn get_add(&self) -> f32 {
+        self.add
+    }
+
+    pub fn get_unscaled(&self) -> f32 {
+        self.unscaled
+    }
+
+    pub fn get_mult(&self) -> f32 {
+        self.mult
+    }
+
+    // The amount of a building with a single level once the multiplier is applied
+    pub fn total(&self) -> f32 {
+        self.total_for(1.)
+    }
+
+    // The amount of a building with this many levels, the unscaled amount is only there once
+    pub fn total_for(&self, levels: f32) -> f32 {
+        (self.add * levels + self.unscaled) * (1. + self.mult)
+    }
+
+    // Adds the amounts of another PM for the same good
+    pub fn combine(&mut self, other: &GoodsAmount) {
+        self.add += other.add;
+        self.unscaled += other.unscaled;
+        self.mult += other.mult;
+    }
 }
 
 impl PM {
-    pub fn from_tree(tree: &Tree, goods: &Goods) -> Result<Self, String> {
+    pub fn from_definition(name: &str, definition: &ProductionMethod, goods: &Goods) -> Self {
 
-        let building_modifiers = tree.get("building_modifiers")?;
-        let level_scaled = building_modifiers.get("level_scaled")?;
-        let worker_scaled = building_modifiers.get("workforce_scaled")?;
+        let modifiers = definition.get_building_modifiers();
+        let scopes = modifiers.get_per_level_scopes();
 
-        let mut input_cost = 0.;
-        for input in worker_scaled.get_children_names_filtered(|name| name.starts_with("goods_input_")) {            
-            input_cost += goods.get_cost(&input.replace("goods_input_", "").replace("_add", "")) as f32
-               * worker_scaled.get(input.clone())?.value()?.parse::<f32>().map_err(|_| "Error parsing output cost")?;
-        }
+        let inputs = goods_amounts(&scopes, modifiers.get_unscaled(), "goods_input_");
+        let outputs = goods_amounts(&scopes, modifiers.get_unscaled(), "goods_output_");
 
-        let mut outpust_cost = 0.;
-        for input in worker_scaled.get_children_names_filtered(|name| name.starts_with("goods_output_")) {
-            outpust_cost += goods.get_cost(&input.replace("goods_output_", "").replace("_add", "")) as f32
-               * worker_scaled.get(input.clone())?.value()?.parse::<f32>().map_err(|_| "Error parsing output cost")?;
-        }
+        let input_cost = goods.value_of(&inputs);
+        let output_cost = goods.value_of(&outputs);
 
-        let mut labor = 0.;
-        for input in level_scaled.get_children_names_filtered(|name| name.starts_with("building_employment_")) {
-            labor += level_scaled.get(input.clone())?.value()?.parse::<f32>().map_err(|_| "Error parsing output cost")?;
+        // building_employment_machinists_add = 1000 -> machinists: 1000
+        let mut employment: HashMap<String, f32> = HashMap::new();
+        for (name, amount) in scopes.iter().flat_map(|scope| scope.iter()) {
+            if let Some(profession) = name.strip_prefix("building_employment_").and_then(|n| n.strip_suffix("_add")) {
+                *employment.entry(profession.to_string()).or_insert(0.) += amount;
+            }
         }
+        let labor = employment.values().sum();
 
-        let mut is_default = false; 
-        if let Ok(is_default_str) = tree.get("is_default") {
-            if let Ok(is_default_value) = is_default_str.value() {
-                is_default = is_default_value == "yes";
+        let mut pm = PM::new(name.to_string(), input_cost, output_cost, labor, definition.is_default());
+        pm.inputs = inputs;
+        pm.outputs = outputs;
+        pm.employment = employment;
+        pm.unlocking_technologies = definition.get_unlocking_technologies().clone();
+        pm.unlocking_laws = definition.get_unlocking_laws().clone();
+        pm.disallowing_laws = definition.get_disallowing_laws().clone();
+        pm
+    }
+}
+
+// Splits goods_input_iron_add = 10 and goods_input_iron_mult = 0.1 into iron -> { add: 10, mult: 0.1 }
+// The adds of the unscaled block go to unscaled, its multipliers apply to the building like the other ones
+fn goods_amounts(scopes: &[&HashMap<String, f32>], unscaled: &HashMap<String, f32>, prefix: &str) -> HashMap<String, GoodsAmount> {
+    let mut amounts: HashMap<String, GoodsAmount> = HashMap::new();
+    let per_level = scopes.iter().flat_map(|scope| scope.iter()).map(|entry| (entry, true));
+    for ((name, value), is_per_level) in per_level.chain(unscaled.iter().map(|entry| (entry, false))) {
+        let Some(rest) = name.strip_prefix(prefix) else {
+            continue;
+        };
+        if let Some(good) = rest.strip_suffix("_add") {
+            let amount = amounts.entry(good.to_string()).or_default();
+            if is_per_level {
+                amount.add += value;
+            } else {
+                amount.unscaled += value;
             }
+        } else if let Some(good) = rest.strip_suffix("_mult") {
+            amounts.entry(good.to_string()).or_default().mult += value;
         }
-
-        return Ok(PM::new(tree.get_name(), input_cost, outpust_cost, labor, is_default));
     }
+    amounts
 }
 
 impl PM {
@@ -55,7 +129,13 @@ impl PM {
             input,
             output,
             labor,
-            is_default
+            is_default,
+            inputs: HashMap::new(),
+            outputs: HashMap::new(),
+            employment: HashMap::new(),
+            unlocking_technologies: Vec::new(),
+            unlocking_laws: Vec::new(),
+            disallowing_laws: Vec::new(),
         }
     }
 
@@ -63,21 +143,64 @@ impl PM {
         self.get_name()
     }
 
-    pub fn get(&self, key: Attribute) -> f32 {
+    // The attributes a PM has on its own
+    // Construction and everything per worker, per construction or per wage depend on the other PMs
+    // and the cost of the building, they are None here, see get_in_buildings
+    pub fn get(&self, key: Attribute) -> Option<f32> {
         match key {
-            Attribute::Input => self.get_input(),
-            Attribute::Output => self.get_output(),
-            Attribute::Labor => self.get_labor(),
-            Attribute::NetOutput => self.get_net_output(),
-            Attribute::EfficiencyPerConstruction => self.get_efficiency_per_construction(),
-            Attribute::EfficiencyPerWorker => self.get_efficiency_per_worker(),
-            _ => panic!("Invalid attribute")
+            Attribute::Input => Some(self.get_input()),
+            Attribute::Output => Some(self.get_output()),
+            Attribute::Labor => Some(self.get_labor()),
+            Attribute::NetOutput => Some(self.get_net_output()),
+            Attribute::Construction
+            | Attribute::EfficiencyPerWorker
+            | Attribute::EfficiencyPerConstruction
+            | Attribute::WageCost
+            | Attribute::ProfitPerWage => None,
         }
     }
 
+    // building -> value of the attribute in that building, for every building that can use the PM
+    // The other groups of the building use their default PM
+    pub fn get_in_buildings(&self, data: &Data, key: Attribute) -> Vec<(String, f32)> {
+        let mut values: Vec<(String, f32)> = data.get_buildings_using(self.name()).iter()
+            .filter_map(|building| Some((building.name().clone(), building.get_pm_data(data, self.name())?.get(key))))
+            .collect();
+        values.sort_by(|a, b| a.0.cmp(&b.0));
+        values
+    }
+
     pub fn default(&self) -> bool {
         self.is_default()
     }
+
+    // Values the goods again, after the prices changed
+    pub fn revalue(&mut self, goods: &Goods) {
+        self.input = goods.value_of(&self.inputs);
+        self.output = goods.value_of(&self.outputs);
+    }
+
+    pub fn get_inputs(&self) -> &HashMap<String, GoodsAmount> {
+        &self.inputs
+    }
+
+    pub fn get_outputs(&self) -> &HashMap<String, GoodsAmount> {
+        &self.outputs
+    }
+
+    pub fn get_employment(&self) -> &HashMap<String, f32> {
+        &self.employment
+    }
+
+    pub fn get_unlocking_technologies(&self) -> &Vec<String> {
+        &self.unlocking_technologies
+    }
+
+    // Whether a country with these laws can pick the PM
+    pub fn allowed_by_laws(&self, laws: &HashSet<String>) -> bool {
+        let unlocked = self.unlocking_laws.is_empty() || self.unlocking_laws.iter().any(|law| laws.contains(law));
+        unlocked && !self.disallowing_laws.iter().any(|law| laws.contains(law))
+    }
 } 
 
 // Private impl
@@ -106,13 +229,46 @@ impl PM {
     fn get_net_output(&self) -> f32 {
         self.get_output() - self.get_input()
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn get_efficiency_per_worker(&self) -> f32 {
-        todo!() // Requires the whole building tree
+    #[test]
+    fn unscaled_amounts_added_once() {
+        let level_scaled = HashMap::from([("goods_input_iron_add".to_string(), 10.), ("goods_input_iron_mult".to_string(), 0.5)]);
+        let unscaled = HashMap::from([("goods_input_iron_add".to_string(), 4.), ("goods_output_steel_add".to_string(), 2.)]);
+        let inputs = goods_amounts(&[&HashMap::new(), &level_scaled], &unscaled, "goods_input_");
+        let outputs = goods_amounts(&[&HashMap::new(), &level_scaled], &unscaled, "goods_output_");
+
+        let iron = &inputs["iron"];
+        assert_eq!((iron.get_add(), iron.get_unscaled(), iron.get_mult()), (10., 4., 0.5));
+        assert_eq!(iron.total(), 21.);
+        // 3 levels: (3 * 10 + 4) * 1.5
+        assert_eq!(iron.total_for(3.), 51.);
+        assert_eq!(outputs["steel"].total_for(3.), 2.);
     }
 
-    fn get_efficiency_per_construction(&self) -> f32 {
-        todo!() // Requires the whole building tree
+    #[test]
+    fn shared_pm_valued_in_each_building() {
+        let data = crate::data::tests::data_from(
+            "shared_pm",
+            "iron = { cost = 40 } tools = { cost = 40 } steel = { cost = 50 }",
+            "building_steel_mills = { production_method_groups = { pmg_steel pmg_automation } required_construction = 400 }
+            building_iron_mine = { production_method_groups = { pmg_iron pmg_automation } required_construction = 200 }",
+            "pmg_steel = { production_methods = { pm_steel } } pmg_iron = { production_methods = { pm_iron } }
+            pmg_automation = { production_methods = { pm_no_automation pm_automation } }",
+            "pm_steel = { building_modifiers = { workforce_scaled = { goods_input_iron_add = 10 goods_output_steel_add = 10 } level_scaled = { building_employment_laborers_add = 1000 } } }
+            pm_iron = { building_modifiers = { workforce_scaled = { goods_output_iron_add = 10 } level_scaled = { building_employment_laborers_add = 1000 } } }
+            pm_no_automation = { is_default = yes }
+            pm_automation = { building_modifiers = { workforce_scaled = { goods_input_tools_add = 1 } level_scaled = { building_employment_laborers_add = -500 } } }",
+        );
+        let pm = data.get_pm("pm_automation").unwrap();
+        assert_eq!(pm.get(Attribute::Construction), None);
+        // 10 * 50 - 10 * 40 - 40 = 60 in the steel mills, 10 * 40 - 40 = 360 in the mine, for 500 workers
+        assert_eq!(pm.get_in_buildings(&data, Attribute::NetOutput), vec![("building_iron_mine".to_string(), 360.), ("building_steel_mills".to_string(), 60.)]);
+        assert_eq!(pm.get_in_buildings(&data, Attribute::Construction), vec![("building_iron_mine".to_string(), 200.), ("building_steel_mills".to_string(), 400.)]);
+        assert_eq!(pm.get_in_buildings(&data, Attribute::EfficiencyPerWorker), vec![("building_iron_mine".to_string(), 0.72), ("building_steel_mills".to_string(), 0.12)]);
     }
 }
-
@@ -1,4 +1,4 @@
-use crate::Tree;
+use vic3_game_data::ProductionMethodGroup;
 
 use super::Data;
 
@@ -9,10 +9,8 @@ pub struct PMG {
 }
 
 impl PMG {
-    pub fn from_tree(tree: &Tree) -> Result<Self, String> {
-        let name = tree.get_name();
-        let pms = tree.get("production_methods")?.get_children_names();
-        Ok(PMG { name, pms })
+    pub fn from_definition(name: &str, definition: &ProductionMethodGroup) -> Self {
+        PMG { name: name.to_string(), pms: definition.get_production_methods().clone() }
     }
 
     pub fn name(&self) -> &String {
@@ -31,6 +29,10 @@ impl PMG {
         Some(result_pm.clone())
     }
 
+    pub fn retain_pms(&mut self, filter: impl Fn(&String) -> bool) {
+        self.pms.retain(|pm| filter(pm));
+    }
+
     pub fn get_pms(&self) -> Vec<String> {
         self.pms.clone()
     }
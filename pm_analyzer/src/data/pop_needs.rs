@@ -0,0 +1,135 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use vic3_game_data::{BuyPackage, Database, PopNeed, PopNeedEntry};
+
+// The buy packages are for this many pops
+pub const PACKAGE_POPULATION: f32 = 10000.;
+
+// What pops buy depending on their wealth
+// A need is split between its goods by their weight, within their min and max supply shares
+// The real split also depends on prices and supply
+pub struct PopNeeds {
+    // need -> (good, share of the need), the shares add up to 1 unless the max supply shares are less
+    needs: HashMap<String, Vec<(String, f32)>>,
+    // wealth -> need -> amount for PACKAGE_POPULATION pops
+    packages: BTreeMap<u32, HashMap<String, f32>>,
+}
+
+impl PopNeeds {
+    pub fn from_databases(needs: &Database<PopNeed>, packages: &Database<BuyPackage>) -> PopNeeds {
+        let needs = needs.iter().map(|(name, need)| {
+            let total: f32 = need.get_entries().iter().map(|e| e.get_weight()).sum();
+            let shares = if total > 0. {
+                split(need.get_entries())
+            } else {
+                vec![(need.get_default().clone(), 1.)]
+            };
+            (name.clone(), shares)
+        }).collect();
+        // wealth_12 -> 12
+        let packages = packages.iter()
+            .filter_map(|(name, package)| Some((name.strip_prefix("wealth_")?.parse().ok()?, package.get_needs().clone())))
+            .collect();
+        PopNeeds { needs, packages }
+    }
+
+    pub fn get_wealth_levels(&self) -> Vec<u32> {
+        self.packages.keys().copied().collect()
+    }
+
+    // Goods bought by PACKAGE_POPULATION pops of this wealth every week
+    pub fn demand(&self, wealth: u32) -> BTreeMap<String, f32> {
+        let mut demand = BTreeMap::new();
+        for (need, amount) in self.packages.get(&wealth).into_iter().flatten() {
+            for (good, share) in self.needs.get(need).into_iter().flatten() {
+                *demand.entry(good.clone()).or_insert(0.) += amount * share;
+            }
+        }
+        demand
+    }
+
+    // Goods bought by these pops, wealth -> number of pops
+    pub fn demand_of(&self, population: &HashMap<u32, f32>) -> HashMap<String, f32> {
+        let mut demand = HashMap::new();
+        for (wealth, pops) in population {
+            for (good, amount) in self.demand(*wealth) {
+                *demand.entry(good).or_insert(0.) += amount * pops / PACKAGE_POPULATION;
+            }
+        }
+        demand
+    }
+
+    // The needs of this wealth that the good is part of
+    pub fn needs_for(&self, wealth: u32, good: &str) -> Vec<String> {
+        let mut needs: Vec<String> = self.packages.get(&wealth).into_iter().flatten()
+            .filter(|(need, _)| self.needs.get(*need).is_some_and(|goods| goods.iter().any(|(g, _)| g == good)))
+            .map(|(need, _)| need.clone())
+            .collect();
+        needs.sort();
+        needs
+    }
+
+    // Every good that pops of some wealth buy
+    pub fn consumed_goods(&self) -> BTreeSet<String> {
+        self.packages.keys().flat_map(|wealth| self.demand(*wealth).into_keys()).collect()
+    }
+}
+
+// Shares of the entries by weight, an entry whose share is above its max or below its min is set to it
+// and the rest is split again between the other entries, until every share is within its bounds
+// When the maxes add up to less than 1 the rest of the need isn't bought
+fn split(entries: &[PopNeedEntry]) -> Vec<(String, f32)> {
+    let mut fixed: Vec<Option<f32>> = vec![None; entries.len()];
+    loop {
+        let left = 1. - fixed.iter().flatten().sum::<f32>();
+        let weight: f32 = entries.iter().zip(&fixed).filter(|(_, f)| f.is_none()).map(|(e, _)| e.get_weight()).sum();
+        let shares: Vec<f32> = entries.iter().zip(&fixed).map(|(entry, fixed)| {
+            fixed.unwrap_or(if weight > 0. { left.max(0.) * entry.get_weight() / weight } else { 0. })
+        }).collect();
+        // The entries above their max first, fixing them can only push the other ones up
+        let above: Vec<usize> = (0..entries.len()).filter(|i| fixed[*i].is_none() && shares[*i] > entries[*i].get_max_supply_share()).collect();
+        let below: Vec<usize> = (0..entries.len()).filter(|i| fixed[*i].is_none() && shares[*i] < entries[*i].get_min_supply_share()).collect();
+        if !above.is_empty() {
+            for i in above {
+                fixed[i] = Some(entries[i].get_max_supply_share());
+            }
+        } else if !below.is_empty() {
+            for i in below {
+                fixed[i] = Some(entries[i].get_min_supply_share());
+            }
+        } else {
+            return entries.iter().zip(shares).map(|(entry, share)| (entry.get_goods().clone(), share)).collect();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vic3_parser::Parser;
+
+    fn pop_needs(needs: &str) -> PopNeeds {
+        let needs = Database::deserialize(&Parser::new().parse(needs.to_string()));
+        let packages = Database::deserialize(&Parser::new().parse("wealth_5 = { goods = { popneed_basic_food = 100 } }".to_string()));
+        PopNeeds::from_databases(&needs, &packages)
+    }
+
+    #[test]
+    fn split_by_weight() {
+        let needs = pop_needs("popneed_basic_food = { default = grain entry = { goods = grain weight = 3 } entry = { goods = fish weight = 1 } }");
+        assert_eq!(needs.demand(5), BTreeMap::from([("fish".to_string(), 25.), ("grain".to_string(), 75.)]));
+        assert!(needs.demand(6).is_empty());
+        let demand = needs.demand_of(&HashMap::from([(5, 20000.)]));
+        assert_eq!(demand, HashMap::from([("fish".to_string(), 50.), ("grain".to_string(), 150.)]));
+    }
+
+    #[test]
+    fn supply_shares_bound_the_split() {
+        let needs = pop_needs("popneed_basic_food = { default = grain
+            entry = { goods = grain weight = 3 max_supply_share = 0.5 }
+            entry = { goods = fish weight = 1 }
+            entry = { goods = meat weight = 0 min_supply_share = 0.25 } }");
+        // meat gets its 25%, grain is held to 50% and fish gets the rest
+        assert_eq!(needs.demand(5), BTreeMap::from([("fish".to_string(), 25.), ("grain".to_string(), 50.), ("meat".to_string(), 25.)]));
+    }
+}
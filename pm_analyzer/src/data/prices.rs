@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use vic3_parser::{de::from_tree, Parser};
+
+// Market prices can't go further than 75% away from the base cost
+pub const MIN_PRICE_FACTOR: f32 = 0.25;
+pub const MAX_PRICE_FACTOR: f32 = 1.75;
+
+// Price of a good in a market where this much of it is sold and bought, the formula of the game:
+// base cost * (1 + 0.75 * (demand - supply) / min(demand, supply)), kept inside of the range the market allows
+pub fn market_price(base_cost: f32, supply: f32, demand: f32) -> f32 {
+    if supply <= 0. && demand <= 0. {
+        return base_cost;
+    }
+    let shift = if supply.min(demand) <= 0. {
+        (demand - supply).signum()
+    } else {
+        (demand - supply) / supply.min(demand)
+    };
+    base_cost * (1. + (MAX_PRICE_FACTOR - 1.) * shift.clamp(-1., 1.))
+}
+
+// Prices the goods are valued at
+// By default every good is at its base cost, a shift moves every price by the same percentage
+// and overrides set the price of a single good
+#[derive(Debug, Clone, Default)]
+pub struct PriceScenario {
+    // 0.1 is +10%
+    shift: f32,
+    overrides: HashMap<String, f32>,
+}
+
+impl PriceScenario {
+    pub fn base() -> PriceScenario {
+        PriceScenario::default()
+    }
+
+    pub fn shifted(percent: f32) -> PriceScenario {
+        PriceScenario { shift: percent / 100., overrides: HashMap::new() }
+    }
+
+    pub fn set_override(&mut self, good: &str, price: f32) {
+        self.overrides.insert(good.to_string(), price);
+    }
+
+    pub fn remove_override(&mut self, good: &str) {
+        self.overrides.remove(good);
+    }
+
+    pub fn extend(&mut self, prices: HashMap<String, f32>) {
+        self.overrides.extend(prices);
+    }
+
+    // A file of good = price lines, e.g. steel = 70
+    pub fn read_overrides(path: &str) -> Result<HashMap<String, f32>, String> {
+        let file = std::fs::File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        let tree = Parser::new().parse_reader(file)?;
+        from_tree(&tree)
+    }
+
+    // Price of a good with this base cost, kept inside of the range the market allows
+    pub fn price(&self, good: &str, base_cost: f32) -> f32 {
+        let price = self.overrides.get(good).copied().unwrap_or(base_cost * (1. + self.shift));
+        price.clamp(base_cost * MIN_PRICE_FACTOR, base_cost * MAX_PRICE_FACTOR)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn market_prices() {
+        assert_eq!(market_price(40., 0., 0.), 40.);
+        assert_eq!(market_price(40., 10., 10.), 40.);
+        // 50% more demand than supply is +37.5%, 50% more supply than demand is -37.5%
+        assert_eq!(market_price(40., 10., 15.), 55.);
+        assert_eq!(market_price(40., 15., 10.), 25.);
+        // Twice as much or nothing on one side is as far as it goes
+        assert_eq!(market_price(40., 10., 30.), 70.);
+        assert_eq!(market_price(40., 0., 5.), 70.);
+        assert_eq!(market_price(40., 30., 10.), 10.);
+        assert_eq!(market_price(40., 5., 0.), 10.);
+    }
+
+    #[test]
+    fn overrides_before_shift() {
+        let mut scenario = PriceScenario::shifted(50.);
+        scenario.set_override("steel", 60.);
+        assert_eq!(scenario.price("steel", 50.), 60.);
+        assert_eq!(scenario.price("iron", 40.), 60.);
+        scenario.remove_override("steel");
+        assert_eq!(scenario.price("steel", 50.), 75.);
+        scenario.extend(HashMap::from([("iron".to_string(), 30.)]));
+        assert_eq!(scenario.price("iron", 40.), 30.);
+    }
+
+    #[test]
+    fn prices_kept_in_market_range() {
+        assert_eq!(PriceScenario::shifted(-90.).price("iron", 40.), 10.);
+        assert_eq!(PriceScenario::shifted(100.).price("iron", 40.), 70.);
+        let mut scenario = PriceScenario::base();
+        scenario.set_override("steel", 1000.);
+        scenario.set_override("coal", 1.);
+        assert_eq!(scenario.price("steel", 50.), 87.5);
+        assert_eq!(scenario.price("coal", 30.), 7.5);
+        assert_eq!(scenario.price("wood", 20.), 20.);
+    }
+}
@@ -0,0 +1,116 @@
+use vic3_game_data::StateRegion;
+
+use super::Data;
+
+// What limits how many levels of a building a state can have
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LevelLimit {
+    // Urban buildings and the like, only the construction it takes
+    None,
+    ArableLand,
+    // capped_resources and the discoverable resources of the state
+    Resources,
+    // The subsistence building of the state grows on the arable land that is left
+    Subsistence,
+}
+
+impl LevelLimit {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LevelLimit::None => "none",
+            LevelLimit::ArableLand => "arable land",
+            LevelLimit::Resources => "resources",
+            LevelLimit::Subsistence => "subsistence",
+        }
+    }
+}
+
+// A building that can be built in a state
+#[derive(Debug)]
+pub struct StateBuilding {
+    building: String,
+    limit: LevelLimit,
+    // None when there is no limit
+    max_level: Option<u32>,
+    // Once every resource of the state is discovered
+    potential_level: Option<u32>,
+    // The ones the scan kept, see --techs, --era and --country
+    pms: Vec<String>,
+}
+
+impl StateBuilding {
+    pub fn get_building(&self) -> &String {
+        &self.building
+    }
+
+    pub fn get_limit(&self) -> LevelLimit {
+        self.limit
+    }
+
+    pub fn get_max_level(&self) -> Option<u32> {
+        self.max_level
+    }
+
+    pub fn get_potential_level(&self) -> Option<u32> {
+        self.potential_level
+    }
+
+    pub fn get_pms(&self) -> &Vec<String> {
+        &self.pms
+    }
+}
+
+// The buildings a state has room for and how many levels of each
+// A building group in arable_resources or capped_resources also covers its children
+pub fn state_buildings(data: &Data, state: &str) -> Result<Vec<StateBuilding>, String> {
+    let region = data.get_state_region(state).ok_or(format!("Unknown state {}", state))?;
+    let mut buildings = data.get_all_buildings();
+    buildings.sort_by(|a, b| a.name().cmp(b.name()));
+    let mut result = Vec::new();
+    for building in buildings {
+        let path = data.get_building_groups().get_path(building.get_building_group());
+        let limit = if building.is_subsistence(data) {
+            if region.get_subsistence_building() != Some(building.name()) {
+                continue;
+            }
+            Some((LevelLimit::Subsistence, region.get_arable_land(), region.get_arable_land()))
+        } else if !building.is_buildable() {
+            continue;
+        } else if building.needs_resources() || has_resource(region, &path) {
+            let (discovered, undiscovered) = resources(region, &path);
+            if discovered + undiscovered == 0 {
+                continue;
+            }
+            Some((LevelLimit::Resources, discovered, discovered + undiscovered))
+        } else if building.needs_arable_land(data) {
+            if !path.iter().any(|group| region.get_arable_resources().contains(group)) {
+                continue;
+            }
+            Some((LevelLimit::ArableLand, region.get_arable_land(), region.get_arable_land()))
+        } else {
+            None
+        };
+        result.push(StateBuilding {
+            building: building.name().clone(),
+            limit: limit.map(|l| l.0).unwrap_or(LevelLimit::None),
+            max_level: limit.map(|l| l.1),
+            potential_level: limit.map(|l| l.2),
+            pms: building.get_pm_names(data),
+        });
+    }
+    Ok(result)
+}
+
+fn has_resource(region: &StateRegion, path: &[String]) -> bool {
+    path.iter().any(|group| region.get_capped_resources().contains_key(group))
+        || region.get_discoverable_resources().iter().any(|r| path.contains(r.get_building_group()))
+}
+
+// (levels there now, levels still to discover)
+fn resources(region: &StateRegion, path: &[String]) -> (u32, u32) {
+    // The closest group wins, a state can cap bg_mining and bg_iron_mining differently
+    let capped = path.iter().find_map(|group| region.get_capped_resources().get(group)).copied().unwrap_or(0);
+    let discoverable = region.get_discoverable_resources().iter().filter(|r| path.contains(r.get_building_group()));
+    let (discovered, undiscovered) = discoverable.fold((0, 0), |(d, u), r| (d + r.get_discovered_amount(), u + r.get_undiscovered_amount()));
+    (capped + discovered, undiscovered)
+}
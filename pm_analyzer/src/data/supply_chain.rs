@@ -0,0 +1,261 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use super::{pm::GoodsAmount, Data};
+
+// A PM of a building that uses or makes goods
+#[derive(Debug)]
+pub struct ChainNode {
+    building: String,
+    pm: String,
+    // good -> amount per level, only the flat amounts, a multiplier alone doesn't make a good
+    inputs: BTreeMap<String, f32>,
+    outputs: BTreeMap<String, f32>,
+}
+
+impl ChainNode {
+    // building_steel_mills/pm_bessemer
+    pub fn id(&self) -> String {
+        format!("{}/{}", self.building, self.pm)
+    }
+}
+
+// Which PM of which building turns which goods into which goods
+// Every PM of every building is in it, not only the default ones
+pub struct SupplyChain {
+    nodes: Vec<ChainNode>,
+    goods: BTreeSet<String>,
+    // Goods that pops buy, see PopNeeds
+    pop_goods: BTreeSet<String>,
+}
+
+impl SupplyChain {
+    pub fn new(data: &Data) -> SupplyChain {
+        let mut buildings = data.get_all_buildings();
+        buildings.sort_by(|a, b| a.name().cmp(b.name()));
+        let mut nodes = Vec::new();
+        for building in buildings {
+            for pm_name in building.get_pm_names(data) {
+                let Some(pm) = data.get_pm(&pm_name) else {
+                    continue;
+                };
+                let flat = |amounts: &HashMap<String, GoodsAmount>| -> BTreeMap<String, f32> {
+                    amounts.iter().map(|(good, a)| (good.clone(), a.get_add() + a.get_unscaled())).filter(|(_, a)| *a > 0.).collect()
+                };
+                let node = ChainNode { building: building.name().clone(), pm: pm_name.clone(), inputs: flat(pm.get_inputs()), outputs: flat(pm.get_outputs()) };
+                if !node.inputs.is_empty() || !node.outputs.is_empty() {
+                    nodes.push(node);
+                }
+            }
+        }
+        let mut goods: BTreeSet<String> = data.get_goods_names().into_iter().collect();
+        for node in &nodes {
+            goods.extend(node.inputs.keys().cloned());
+            goods.extend(node.outputs.keys().cloned());
+        }
+        let pop_goods = data.get_pop_needs().consumed_goods();
+        goods.extend(pop_goods.iter().cloned());
+        SupplyChain { nodes, goods, pop_goods }
+    }
+
+    pub fn get_nodes(&self) -> &Vec<ChainNode> {
+        &self.nodes
+    }
+
+    // Goods that no PM makes, whether something uses them or not
+    pub fn goods_without_producer(&self) -> Vec<String> {
+        self.goods.iter().filter(|good| !self.nodes.iter().any(|n| n.outputs.contains_key(*good))).cloned().collect()
+    }
+
+    // Goods that are made but neither buildings nor pops use
+    pub fn dead_end_goods(&self) -> Vec<String> {
+        self.goods.iter()
+            .filter(|good| self.nodes.iter().any(|n| n.outputs.contains_key(*good)))
+            .filter(|good| !self.nodes.iter().any(|n| n.inputs.contains_key(*good)))
+            .filter(|good| !self.pop_goods.contains(*good))
+            .cloned().collect()
+    }
+
+    // Goods that pops buy but no building uses
+    pub fn pop_only_goods(&self) -> Vec<String> {
+        self.pop_goods.iter().filter(|good| !self.nodes.iter().any(|n| n.inputs.contains_key(*good))).cloned().collect()
+    }
+
+    // Groups of goods that depend on each other, e.g. tools need iron and iron mines need tools
+    // A good is needed for another when a PM uses the first one to make the second one
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        let mut edges: BTreeMap<&String, BTreeSet<&String>> = BTreeMap::new();
+        for node in &self.nodes {
+            for input in node.inputs.keys() {
+                edges.entry(input).or_default().extend(node.outputs.keys());
+            }
+        }
+        strongly_connected(&self.goods.iter().collect::<Vec<&String>>(), &edges).into_iter()
+            .filter(|component| component.len() > 1 || edges.get(component[0]).is_some_and(|e| e.contains(component[0])))
+            .map(|component| component.into_iter().cloned().collect())
+            .collect()
+    }
+
+    // Goods are ellipses and PMs are boxes, goods with a problem are colored:
+    // red when nothing makes them, orange when nothing uses them, blue when they are part of a cycle
+    // Goods that pops buy have a double border
+    pub fn to_dot(&self, localize: impl Fn(&str) -> String) -> String {
+        let without_producer = self.goods_without_producer();
+        let dead_ends = self.dead_end_goods();
+        let in_cycle: Vec<String> = self.cycles().into_iter().flatten().collect();
+
+        // Ids are quoted like the labels, a key can have any character in a mod
+        let good_id = |good: &str| escape(&format!("good:{}", good));
+
+        let mut dot = String::from("digraph supply_chain {\n    rankdir=LR;\n");
+        for good in &self.goods {
+            let color = if without_producer.contains(good) {
+                "red"
+            } else if dead_ends.contains(good) {
+                "orange"
+            } else if in_cycle.contains(good) {
+                "lightblue"
+            } else {
+                "white"
+            };
+            let peripheries = if self.pop_goods.contains(good) { 2 } else { 1 };
+            dot.push_str(&format!("    \"{}\" [shape=ellipse, style=filled, fillcolor={}, peripheries={}, label=\"{}\"];\n", good_id(good), color, peripheries, escape(&localize(good))));
+        }
+        for node in &self.nodes {
+            let node_id = escape(&node.id());
+            dot.push_str(&format!("    \"{}\" [shape=box, label=\"{}\\n{}\"];\n", node_id, escape(&localize(&node.building)), escape(&localize(&node.pm))));
+            for (good, amount) in &node.inputs {
+                dot.push_str(&format!("    \"{}\" -> \"{}\" [label=\"{}\"];\n", good_id(good), node_id, amount));
+            }
+            for (good, amount) in &node.outputs {
+                dot.push_str(&format!("    \"{}\" -> \"{}\" [label=\"{}\"];\n", node_id, good_id(good), amount));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let goods: Vec<serde_json::Value> = self.goods.iter().map(|good| serde_json::json!({
+            "id": format!("good:{}", good),
+            "type": "good",
+            "good": good,
+            "pop_need": self.pop_goods.contains(good)
+        })).collect();
+        let pms: Vec<serde_json::Value> = self.nodes.iter().map(|node| serde_json::json!({
+            "id": node.id(),
+            "type": "pm",
+            "building": node.building,
+            "pm": node.pm
+        })).collect();
+        let mut edges = Vec::new();
+        for node in &self.nodes {
+            for (good, amount) in &node.inputs {
+                edges.push(serde_json::json!({ "from": format!("good:{}", good), "to": node.id(), "amount": amount }));
+            }
+            for (good, amount) in &node.outputs {
+                edges.push(serde_json::json!({ "from": node.id(), "to": format!("good:{}", good), "amount": amount }));
+            }
+        }
+        serde_json::json!({
+            "nodes": goods.into_iter().chain(pms).collect::<Vec<serde_json::Value>>(),
+            "edges": edges,
+            "goods_without_producer": self.goods_without_producer(),
+            "dead_end_goods": self.dead_end_goods(),
+            "pop_only_goods": self.pop_only_goods(),
+            "cycles": self.cycles()
+        })
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Tarjan's algorithm, every component is sorted and so is the list of components
+fn strongly_connected<'a>(vertices: &[&'a String], edges: &BTreeMap<&'a String, BTreeSet<&'a String>>) -> Vec<Vec<&'a String>> {
+    struct State<'a> {
+        index: HashMap<&'a String, usize>,
+        lowlink: HashMap<&'a String, usize>,
+        stack: Vec<&'a String>,
+        components: Vec<Vec<&'a String>>,
+    }
+
+    fn visit<'a>(vertex: &'a String, edges: &BTreeMap<&'a String, BTreeSet<&'a String>>, state: &mut State<'a>) {
+        let index = state.index.len();
+        state.index.insert(vertex, index);
+        state.lowlink.insert(vertex, index);
+        state.stack.push(vertex);
+        for next in edges.get(vertex).into_iter().flatten() {
+            if !state.index.contains_key(next) {
+                visit(next, edges, state);
+                let low = state.lowlink[vertex].min(state.lowlink[next]);
+                state.lowlink.insert(vertex, low);
+            } else if state.stack.contains(next) {
+                let low = state.lowlink[vertex].min(state.index[next]);
+                state.lowlink.insert(vertex, low);
+            }
+        }
+        if state.lowlink[vertex] == state.index[vertex] {
+            let mut component = Vec::new();
+            while let Some(top) = state.stack.pop() {
+                component.push(top);
+                if top == vertex {
+                    break;
+                }
+            }
+            component.sort();
+            state.components.push(component);
+        }
+    }
+
+    let mut state = State { index: HashMap::new(), lowlink: HashMap::new(), stack: Vec::new(), components: Vec::new() };
+    for vertex in vertices {
+        if !state.index.contains_key(vertex) {
+            visit(vertex, edges, &mut state);
+        }
+    }
+    state.components.sort();
+    state.components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(building: &str, inputs: &[&str], outputs: &[&str]) -> ChainNode {
+        let amounts = |goods: &[&str]| goods.iter().map(|good| (good.to_string(), 1.)).collect();
+        ChainNode { building: building.to_string(), pm: "pm".to_string(), inputs: amounts(inputs), outputs: amounts(outputs) }
+    }
+
+    #[test]
+    fn components() {
+        let names: Vec<String> = ["a", "b", "c", "d", "e", "f"].iter().map(|n| n.to_string()).collect();
+        let [a, b, c, d, e, f] = [&names[0], &names[1], &names[2], &names[3], &names[4], &names[5]];
+        // a <-> b, c -> c, d -> e -> f
+        let edges: BTreeMap<&String, BTreeSet<&String>> = BTreeMap::from([
+            (a, BTreeSet::from([b])),
+            (b, BTreeSet::from([a])),
+            (c, BTreeSet::from([c])),
+            (d, BTreeSet::from([e])),
+            (e, BTreeSet::from([f])),
+        ]);
+        let components = strongly_connected(&names.iter().collect::<Vec<&String>>(), &edges);
+        assert_eq!(components, vec![vec![a, b], vec![c], vec![d], vec![e], vec![f]]);
+    }
+
+    #[test]
+    fn cycles_and_escaped_ids() {
+        let chain = SupplyChain {
+            nodes: vec![
+                node("building_tools", &["iron"], &["tools"]),
+                node("building_iron_mine", &["tools"], &["iron"]),
+                node("building_\"quoted\"", &["coal"], &["coal", "steel"]),
+            ],
+            goods: ["coal", "iron", "steel", "tools"].iter().map(|g| g.to_string()).collect(),
+            pop_goods: BTreeSet::new(),
+        };
+        assert_eq!(chain.cycles(), vec![vec!["coal".to_string()], vec!["iron".to_string(), "tools".to_string()]]);
+        let dot = chain.to_dot(|key| key.to_string());
+        assert!(dot.contains("    \"building_\\\"quoted\\\"/pm\" -> \"good:steel\" [label=\"1\"];\n"));
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use super::{pm::GoodsAmount, Attribute, Data};
+
+// A good whose base cost is this many times above or below the median cost per worker is flagged
+pub const PRICE_OUTLIER_FACTOR: f64 = 2.;
+
+// What it takes to make one unit of a good, its inputs and their inputs included
+#[derive(Debug)]
+pub struct GoodCost {
+    good: String,
+    // The building whose PMs make the good, None when nothing makes it
+    producer: Option<String>,
+    pms: Vec<String>,
+    // Only in the producing building
+    direct_labor: f64,
+    // The whole chain
+    labor: f64,
+    construction: f64,
+    // Goods of the chain that nothing makes, they are counted as free
+    missing_inputs: Vec<String>,
+}
+
+impl GoodCost {
+    pub fn get_good(&self) -> &String {
+        &self.good
+    }
+
+    pub fn get_producer(&self) -> Option<&String> {
+        self.producer.as_ref()
+    }
+
+    pub fn get_pms(&self) -> &Vec<String> {
+        &self.pms
+    }
+
+    pub fn get_direct_labor(&self) -> f64 {
+        self.direct_labor
+    }
+
+    pub fn get_labor(&self) -> f64 {
+        self.labor
+    }
+
+    pub fn get_construction(&self) -> f64 {
+        self.construction
+    }
+
+    pub fn get_missing_inputs(&self) -> &Vec<String> {
+        &self.missing_inputs
+    }
+}
+
+// One production process per good, what a building uses and makes per level
+#[derive(Clone)]
+struct Process {
+    building: String,
+    pms: Vec<String>,
+    labor: f64,
+    construction: f64,
+    inputs: BTreeMap<String, f64>,
+    outputs: BTreeMap<String, f64>,
+}
+
+impl Process {
+    // Share of the building's output value that goes to this good
+    // Inputs, workers and construction of a building that makes several goods are split by value
+    fn share(&self, good: &str, data: &Data) -> f64 {
+        let value = |good: &str, amount: f64| amount * data.get_goods().get_price(good) as f64;
+        let total: f64 = self.outputs.iter().map(|(g, a)| value(g, *a)).sum();
+        if total <= 0. {
+            return 1. / self.outputs.len() as f64;
+        }
+        value(good, self.outputs[good]) / total
+    }
+}
+
+// Leontief model of the economy: every good is made by a single process and the total labor
+// of a good is the labor of its process plus the total labor of the inputs it needs
+//   labor[g] = direct[g] + sum over inputs h of (h needed per unit of g) * labor[h]
+// The same goes for construction
+pub struct TrueCost {
+    costs: Vec<GoodCost>,
+}
+
+impl TrueCost {
+    // pms replace the default PM of their group in every building that has them
+    // When several buildings make a good, the one needing the fewest workers per unit is used
+    pub fn new(data: &Data, pms: &[String]) -> Result<TrueCost, String> {
+        let processes = producers(data, pms);
+        let goods: Vec<String> = data.get_goods_names().into_iter().collect::<BTreeSet<String>>().into_iter().collect();
+        let index = |good: &str| goods.iter().position(|g| g == good);
+
+        // (I - A^T) x = direct, A[h][g] is how much of h one unit of g needs
+        let n = goods.len();
+        let mut matrix = vec![vec![0.; n]; n];
+        let mut labor = vec![0.; n];
+        let mut construction = vec![0.; n];
+        for (i, good) in goods.iter().enumerate() {
+            matrix[i][i] = 1.;
+            let Some(process) = processes.get(good) else {
+                continue;
+            };
+            let amount = process.outputs[good];
+            let share = process.share(good, data);
+            labor[i] = process.labor * share / amount;
+            construction[i] = process.construction * share / amount;
+            for (input, input_amount) in &process.inputs {
+                if let Some(j) = index(input) {
+                    matrix[i][j] -= input_amount * share / amount;
+                }
+            }
+        }
+        // A cycle that needs more of a good than it makes has no solution, or a negative one
+        let unsolvable = "The supply chain uses more of some goods than it makes, they have no true cost".to_string();
+        let total_labor = solve(matrix.clone(), labor.clone()).ok_or(unsolvable.clone())?;
+        let total_construction = solve(matrix, construction).ok_or(unsolvable.clone())?;
+        if total_labor.iter().chain(&total_construction).any(|x| *x < -1e-6) {
+            return Err(unsolvable);
+        }
+
+        let costs = goods.iter().enumerate().map(|(i, good)| {
+            let process = processes.get(good);
+            GoodCost {
+                good: good.clone(),
+                producer: process.map(|p| p.building.clone()),
+                pms: process.map(|p| p.pms.clone()).unwrap_or_default(),
+                direct_labor: labor[i],
+                labor: total_labor[i],
+                construction: total_construction[i],
+                missing_inputs: chain_of(good, &processes).into_iter().filter(|g| !processes.contains_key(g)).collect(),
+            }
+        }).collect();
+        Ok(TrueCost { costs })
+    }
+
+    pub fn get_costs(&self) -> &Vec<GoodCost> {
+        &self.costs
+    }
+
+    // Base cost per worker of the chain, the goods that are made by nobody or need no workers are left out
+    pub fn cost_per_worker(&self, data: &Data, good: &GoodCost) -> Option<f64> {
+        if good.producer.is_none() || good.labor <= 0. {
+            return None;
+        }
+        Some(data.get_goods().get_cost(&good.good) as f64 / good.labor)
+    }
+
+    // Cost per worker compared to the median of every good, 1 is the median
+    pub fn relative_cost(&self, data: &Data, good: &GoodCost) -> Option<f64> {
+        let mut all: Vec<f64> = self.costs.iter().filter_map(|g| self.cost_per_worker(data, g)).collect();
+        if all.is_empty() {
+            return None;
+        }
+        all.sort_by(|a, b| a.total_cmp(b));
+        let median = all[all.len() / 2];
+        Some(self.cost_per_worker(data, good)? / median)
+    }
+}
+
+// The process used for each good, see TrueCost::new
+fn producers(data: &Data, pms: &[String]) -> BTreeMap<String, Process> {
+    let mut buildings = data.get_all_buildings();
+    buildings.sort_by(|a, b| a.name().cmp(b.name()));
+    let mut processes: BTreeMap<String, Process> = BTreeMap::new();
+    for building in buildings {
+        let chosen = building.get_chosen_pms(data, pms);
+        let Some(efficiency) = building.get_combination_data(data, &chosen) else {
+            continue;
+        };
+        let totals = |amounts: &HashMap<String, GoodsAmount>| -> BTreeMap<String, f64> {
+            amounts.iter().map(|(good, a)| (good.clone(), a.total() as f64)).filter(|(_, a)| *a > 0.).collect()
+        };
+        let process = Process {
+            building: building.name().clone(),
+            pms: chosen,
+            labor: efficiency.get(Attribute::Labor) as f64,
+            construction: efficiency.get(Attribute::Construction) as f64,
+            inputs: totals(efficiency.get_inputs()),
+            outputs: totals(efficiency.get_outputs()),
+        };
+        for good in process.outputs.keys() {
+            let per_unit = |p: &Process| p.labor * p.share(good, data) / p.outputs[good];
+            if processes.get(good).is_none_or(|current| per_unit(&process) < per_unit(current)) {
+                processes.insert(good.clone(), process.clone());
+            }
+        }
+    }
+    processes
+}
+
+// Every good needed, directly or not, to make this one
+fn chain_of(good: &str, processes: &BTreeMap<String, Process>) -> BTreeSet<String> {
+    let mut chain = BTreeSet::new();
+    let mut stack = vec![good.to_string()];
+    while let Some(good) = stack.pop() {
+        if let Some(process) = processes.get(&good) {
+            for input in process.inputs.keys() {
+                if chain.insert(input.clone()) {
+                    stack.push(input.clone());
+                }
+            }
+        }
+    }
+    chain
+}
+
+// Gaussian elimination with partial pivoting, None when the matrix is singular
+fn solve(mut matrix: Vec<Vec<f64>>, mut rhs: Vec<f64>) -> Option<Vec<f64>> {
+    let n = rhs.len();
+    for column in 0..n {
+        let pivot = (column..n).max_by(|a, b| matrix[*a][column].abs().total_cmp(&matrix[*b][column].abs()))?;
+        if matrix[pivot][column].abs() < 1e-12 {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        rhs.swap(column, pivot);
+        for row in column + 1..n {
+            let factor = matrix[row][column] / matrix[column][column];
+            let pivot_row = matrix[column].clone();
+            for (value, pivot_value) in matrix[row].iter_mut().zip(pivot_row).skip(column) {
+                *value -= factor * pivot_value;
+            }
+            rhs[row] -= factor * rhs[column];
+        }
+    }
+    let mut result = vec![0.; n];
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|k| matrix[row][k] * result[k]).sum();
+        result[row] = (rhs[row] - sum) / matrix[row][row];
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::tests::data_from;
+
+    fn cost<'a>(true_cost: &'a TrueCost, good: &str) -> &'a GoodCost {
+        true_cost.get_costs().iter().find(|cost| cost.good == good).unwrap()
+    }
+
+    #[test]
+    fn solve_small_system() {
+        // 2x + y = 3, x + 3y = 5
+        let result = solve(vec![vec![2., 1.], vec![1., 3.]], vec![3., 5.]).unwrap();
+        assert!((result[0] - 0.8).abs() < 1e-9 && (result[1] - 1.4).abs() < 1e-9);
+        // The pivot has to be looked for below the diagonal
+        assert_eq!(solve(vec![vec![0., 1.], vec![1., 0.]], vec![2., 3.]), Some(vec![3., 2.]));
+        assert_eq!(solve(vec![vec![1., 2.], vec![2., 4.]], vec![1., 2.]), None);
+    }
+
+    #[test]
+    fn labor_of_the_inputs_added() {
+        let data = data_from(
+            "true_cost_chain",
+            "iron = { cost = 40 } steel = { cost = 50 }",
+            "building_iron_mine = { production_method_groups = { pmg_iron } required_construction = 100 }
+            building_steel_mills = { production_method_groups = { pmg_steel } required_construction = 300 }",
+            "pmg_iron = { production_methods = { pm_iron } } pmg_steel = { production_methods = { pm_steel } }",
+            "pm_iron = { building_modifiers = { workforce_scaled = { goods_output_iron_add = 10 } level_scaled = { building_employment_laborers_add = 1000 } } }
+            pm_steel = { building_modifiers = { workforce_scaled = { goods_input_iron_add = 20 goods_output_steel_add = 10 } level_scaled = { building_employment_laborers_add = 2000 } } }",
+        );
+        let true_cost = TrueCost::new(&data, &[]).unwrap();
+        let (iron, steel) = (cost(&true_cost, "iron"), cost(&true_cost, "steel"));
+        assert_eq!((iron.get_direct_labor(), iron.get_labor(), iron.get_construction()), (100., 100., 10.));
+        // 2 iron per steel: labor[steel] = direct[steel] + 2 * labor[iron]
+        assert_eq!(steel.get_direct_labor(), 200.);
+        assert!((steel.get_labor() - (200. + 2. * 100.)).abs() < 1e-9);
+        assert!((steel.get_construction() - (30. + 2. * 10.)).abs() < 1e-9);
+        assert_eq!(steel.get_producer(), Some(&"building_steel_mills".to_string()));
+        assert!(steel.get_missing_inputs().is_empty());
+    }
+
+    #[test]
+    fn unproductive_cycle_unsolvable() {
+        // Every iron takes a tool and every tool takes two iron
+        let data = data_from(
+            "true_cost_cycle",
+            "iron = { cost = 40 } tools = { cost = 40 }",
+            "building_iron_mine = { production_method_groups = { pmg_iron } } building_tooling_workshops = { production_method_groups = { pmg_tools } }",
+            "pmg_iron = { production_methods = { pm_iron } } pmg_tools = { production_methods = { pm_tools } }",
+            "pm_iron = { building_modifiers = { workforce_scaled = { goods_input_tools_add = 10 goods_output_iron_add = 10 } level_scaled = { building_employment_laborers_add = 1000 } } }
+            pm_tools = { building_modifiers = { workforce_scaled = { goods_input_iron_add = 20 goods_output_tools_add = 10 } level_scaled = { building_employment_laborers_add = 1000 } } }",
+        );
+        let error = TrueCost::new(&data, &[]).err().unwrap();
+        assert!(error.contains("uses more of some goods than it makes"));
+    }
+
+    #[test]
+    fn several_outputs_split_by_value() {
+        // 400 of iron and 300 of coal
+        let data = data_from(
+            "true_cost_split",
+            "iron = { cost = 40 } coal = { cost = 30 }",
+            "building_mine = { production_method_groups = { pmg_mine } }",
+            "pmg_mine = { production_methods = { pm_mine } }",
+            "pm_mine = { building_modifiers = { workforce_scaled = { goods_output_iron_add = 10 goods_output_coal_add = 10 } level_scaled = { building_employment_laborers_add = 700 } } }",
+        );
+        let true_cost = TrueCost::new(&data, &[]).unwrap();
+        assert!((cost(&true_cost, "iron").get_labor() - 700. * 4. / 7. / 10.).abs() < 1e-9);
+        assert!((cost(&true_cost, "coal").get_labor() - 700. * 3. / 7. / 10.).abs() < 1e-9);
+    }
+}
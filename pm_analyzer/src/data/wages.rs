@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use vic3_parser::{de::from_tree, Parser};
+
+// Wage assumed for each profession, relative to the wage of a laborer
+// Real wages depend on the market and laws, these are only used to compare PMs with each other
+const DEFAULT_WAGES: [(&str, f32); 15] = [
+    ("slaves", 0.),
+    ("peasants", 0.5),
+    ("laborers", 1.),
+    ("farmers", 1.),
+    ("soldiers", 1.),
+    ("machinists", 1.5),
+    ("clerks", 2.),
+    ("shopkeepers", 2.),
+    ("clergymen", 2.),
+    ("bureaucrats", 2.),
+    ("engineers", 3.),
+    ("academics", 3.),
+    ("officers", 3.),
+    ("aristocrats", 5.),
+    ("capitalists", 8.),
+];
+
+pub struct Wages {
+    wages: HashMap<String, f32>,
+}
+
+impl Wages {
+    pub fn default() -> Wages {
+        Wages {
+            wages: DEFAULT_WAGES.iter().map(|(profession, wage)| (profession.to_string(), *wage)).collect(),
+        }
+    }
+
+    // A file of profession = wage lines, e.g. engineers = 4
+    // The professions that are not in the file keep their default wage
+    pub fn from_file(path: &str) -> Result<Wages, String> {
+        let file = std::fs::File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        let tree = Parser::new().parse_reader(file)?;
+        let overrides: HashMap<String, f32> = from_tree(&tree)?;
+        let mut wages = Wages::default();
+        wages.wages.extend(overrides);
+        Ok(wages)
+    }
+
+    // Professions that are not known are paid like laborers
+    pub fn get(&self, profession: &str) -> f32 {
+        *self.wages.get(profession).unwrap_or(&1.)
+    }
+
+    // Wage bill of a building, negative employment (e.g. from automation) is counted as zero
+    pub fn cost(&self, employment: &HashMap<String, f32>) -> f32 {
+        employment.iter().map(|(profession, amount)| amount.max(0.) * self.get(profession)).sum()
+    }
+}
@@ -1,27 +1,287 @@
 #![allow(dead_code)]
 mod scanner;
 mod data;
+mod options;
+mod save;
 
-use std::io::Write;
+use std::{collections::HashMap, io::Write};
 
-use vic3_parser::Tree;
-use data::{Attribute::*, Data};
+use vic3_parser::localization::MissingKeyReport;
+use data::{Attribute::{self, *}, Balance, Data, Scenario, SupplyChain, state_buildings, PACKAGE_POPULATION, TrueCost, PRICE_OUTLIER_FACTOR, MAX_PRICE_FACTOR, MIN_PRICE_FACTOR};
+use options::Options;
+use save::SaveUsage;
 
 fn main() -> Result<(), String> {
-    let data = scanner::scan()?;
+    let options = Options::from_args()?;
+    if let Some((old_game, new_game)) = &options.patch_diff {
+        // Only the two installs are scanned, the mod from scan.cfg is not used
+        let old = scanner::scan_game(&options, old_game)?;
+        let new = scanner::scan_game(&options, new_game)?;
+        write_patch_diff(&old, &new);
+        return Ok(());
+    }
+    let mut data = scanner::scan(&options)?;
+    data.set_price_scenario(scanner::price_scenario(&options, &data)?);
 
     //dbg!(data.get_pm("pm_improved_food_manufactories"));
     //dbg!(data.get_pmg("pmg_base_building_food_industry"));
     //dbg!(data.get_building("building_food_industry"));
     //dbg!(data.get_building("pm_basic_distillation_liquor"));
-    write_csv(&data);
+    if options.missing_localization {
+        write_missing_localization(&data)?;
+    } else if let Some(pm) = &options.research {
+        write_research(&data, &options, pm)?;
+    } else if options.supply_chain {
+        write_supply_chain(&data)?;
+    } else if options.true_cost {
+        write_true_cost(&data, &options.pms)?;
+    } else if options.state_report {
+        write_state_report(&data, &options)?;
+    } else if options.buildings {
+        write_buildings(&data);
+    } else if options.pop_needs {
+        write_pop_needs(&data);
+    } else if let Some(path) = &options.balance {
+        write_balance(&data, path)?;
+    } else if options.combinations {
+        write_combinations(&data);
+    } else if options.sensitivity {
+        write_sensitivity(&mut data);
+    } else if options.diff_vanilla {
+        write_vanilla_diff()?;
+    } else if let Some(save) = &options.save {
+        let usage = SaveUsage::from_save(save)?;
+        write_save_comparison(&data, &usage);
+    } else {
+        write_csv(&data);
+    }
+    Ok(())
+}
+
+// Checks every building, PM, PMG and good against all the available languages
+// Writes the result both as text and as JSON
+fn write_missing_localization(data: &Data) -> Result<(), String> {
+    let localizations = scanner::scan_all_localizations();
+    let report = MissingKeyReport::new(&data.get_localization_keys(), &localizations);
+
+    std::fs::write("missing_localization.txt", report.to_text()).map_err(|e| e.to_string())?;
+    std::fs::write("missing_localization.json", report.to_json().to_string()).map_err(|e| e.to_string())?;
+    Ok(())
+}
+// Lists what the mod changes in the vanilla PMs, as text and as JSON
+fn write_vanilla_diff() -> Result<(), String> {
+    let diff = scanner::diff_with_vanilla()?;
+
+    std::fs::write("pm_diff.txt", diff.to_text()).map_err(|e| e.to_string())?;
+    std::fs::write("pm_diff.json", diff.to_json().to_string()).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// The technologies to research before the PM can be used, for every building that has it
+// in an order they can be researched in, leaving out what --techs and --era already give
+fn write_research(data: &Data, options: &Options, pm_name: &str) -> Result<(), String> {
+    let pm = data.get_pm(pm_name).ok_or(format!("Unknown PM {}", pm_name))?;
+    let technologies = data.get_technologies();
+    let researched = scanner::unlocked_technologies(options, data).unwrap_or_default();
+
+    let mut buildings = data.get_buildings_using(pm_name);
+    buildings.sort_by(|a, b| a.name().cmp(b.name()));
+    let mut text = format!("{} ({})\n", data.localize(pm_name), pm_name);
+    for building in buildings {
+        let needed: Vec<String> = pm.get_unlocking_technologies().iter().chain(building.get_unlocking_technologies()).cloned().collect();
+        let order: Vec<String> = technologies.research_order(&needed).into_iter().filter(|t| !researched.contains(t)).collect();
+        text.push_str(&format!("\n{} ({}): ", data.localize(building.name()), building.name()));
+        if order.is_empty() {
+            text.push_str("nothing to research\n");
+            continue;
+        }
+        text.push_str(&format!("{} technologies\n", order.len()));
+        for technology in order {
+            let era = technologies.get(&technology).map(|t| t.get_era()).unwrap_or(0);
+            text.push_str(&format!("  era {} {} ({})\n", era, data.localize(&technology), technology));
+        }
+    }
+    std::fs::write("research.txt", text).map_err(|e| e.to_string())
+}
+
+// The graph of goods and the PMs that make and use them, as Graphviz DOT and as JSON
+// Both flag the goods that nothing makes, the ones that nothing uses and the cycles
+fn write_supply_chain(data: &Data) -> Result<(), String> {
+    let chain = SupplyChain::new(data);
+
+    std::fs::write("supply_chain.dot", chain.to_dot(|key| data.localize(key))).map_err(|e| e.to_string())?;
+    std::fs::write("supply_chain.json", chain.to_json().to_string()).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Workers and construction per unit of each good once its inputs, and theirs, are made too
+// The base cost per worker of the chain is compared to the median to find goods priced out of line
+fn write_true_cost(data: &Data, pms: &[String]) -> Result<(), String> {
+    for pm in pms {
+        if data.get_pm(pm).is_none() {
+            return Err(format!("Unknown PM {}", pm));
+        }
+    }
+    let true_cost = TrueCost::new(data, pms)?;
+
+    let mut grid: Vec<Vec<String>> = Vec::new();
+    grid.push(vec!["Good".to_string(), "Producer".to_string(), "PMs".to_string(), "Base Cost".to_string(), "Direct Labor Per Unit".to_string(), "Labor Per Unit".to_string(), "Construction Per Unit".to_string(), "Cost Per Hundred Workers".to_string(), "Relative To Median".to_string(), "Flag".to_string(), "Missing Inputs".to_string()]);
+    for good in true_cost.get_costs() {
+        let relative = true_cost.relative_cost(data, good);
+        let flag = match relative {
+            Some(r) if r >= PRICE_OUTLIER_FACTOR => "overpriced",
+            Some(r) if r <= 1. / PRICE_OUTLIER_FACTOR => "underpriced",
+            Some(_) => "",
+            None if good.get_producer().is_none() => "no producer",
+            None => "",
+        };
+        grid.push(vec![
+            data.localize(good.get_good()),
+            good.get_producer().map(|b| data.localize(b)).unwrap_or_default(),
+            good.get_pms().iter().map(|pm| data.localize(pm)).collect::<Vec<String>>().join(" + "),
+            data.get_cost(good.get_good()).to_string(),
+            good.get_direct_labor().to_string(),
+            good.get_labor().to_string(),
+            good.get_construction().to_string(),
+            true_cost.cost_per_worker(data, good).map(|c| (c * 100.).to_string()).unwrap_or_default(),
+            relative.map(|r| r.to_string()).unwrap_or_default(),
+            flag.to_string(),
+            good.get_missing_inputs().iter().map(|g| data.localize(g)).collect::<Vec<String>>().join(", "),
+        ]);
+    }
+    write_grid("true_cost.csv", grid);
+    Ok(())
+}
+
+// Supply, demand and price of every good and the profit of every building once the market of the scenario settles
+fn write_balance(data: &Data, path: &str) -> Result<(), String> {
+    let scenario = Scenario::read(path)?;
+    let balance = Balance::simulate(data, &scenario)?;
+    if balance.is_converged() {
+        println!("Balanced after {} iterations", balance.get_iterations());
+    } else {
+        println!("Not balanced after {} iterations, the results are the last ones", balance.get_iterations());
+    }
+
+    let mut grid: Vec<Vec<String>> = Vec::new();
+    grid.push(vec!["Good".to_string(), "Supply".to_string(), "Demand".to_string(), "Pop Demand".to_string(), "Balance".to_string(), "Base Cost".to_string(), "Price".to_string(), "Price Change".to_string()]);
+    for good in balance.get_goods() {
+        let cost = data.get_cost(good.get_good()) as f32;
+        grid.push(vec![
+            data.localize(good.get_good()),
+            good.get_supply().to_string(),
+            good.get_demand().to_string(),
+            good.get_pop_demand().to_string(),
+            (good.get_supply() - good.get_demand()).to_string(),
+            cost.to_string(),
+            good.get_price().to_string(),
+            if cost > 0. { format!("{:+.1}%", (good.get_price() / cost - 1.) * 100.) } else { "".to_string() },
+        ]);
+    }
+    write_grid("balance_goods.csv", grid);
+
+    let mut grid: Vec<Vec<String>> = Vec::new();
+    grid.push(vec!["Building".to_string(), "Levels".to_string(), "PMs".to_string(), "Throughput".to_string(), "Revenue".to_string(), "Input Cost".to_string(), "Profit".to_string(), "Profit Per Level".to_string(), "Wage Cost".to_string(), "Profit Per Wage".to_string()]);
+    for building in balance.get_buildings() {
+        grid.push(vec![
+            data.localize(building.get_building()),
+            building.get_levels().to_string(),
+            building.get_pms().iter().map(|pm| data.localize(pm)).collect::<Vec<String>>().join(" + "),
+            building.get_throughput().to_string(),
+            building.get_revenue().to_string(),
+            building.get_input_cost().to_string(),
+            building.get_profit().to_string(),
+            (building.get_profit() / building.get_levels() as f32).to_string(),
+            building.get_wage_cost().to_string(),
+            // Buildings without workers have no wages to compare with
+            if building.get_wage_cost() > 0. { (building.get_profit() / building.get_wage_cost()).to_string() } else { "".to_string() },
+        ]);
+    }
+    write_grid("balance_buildings.csv", grid);
     Ok(())
 }
+
+// How many levels of each building the states have room for and the PMs the buildings can use
+fn write_state_report(data: &Data, options: &Options) -> Result<(), String> {
+    let states = match (&options.states, data.get_country()) {
+        (states, _) if !states.is_empty() => states.clone(),
+        (_, Some(country)) => country.get_states().clone(),
+        _ => return Err("--state-report needs --states or --country".to_string()),
+    };
+
+    let level = |level: Option<u32>| level.map(|l| l.to_string()).unwrap_or("no limit".to_string());
+    let mut grid: Vec<Vec<String>> = Vec::new();
+    grid.push(vec!["State".to_string(), "Building".to_string(), "Limit".to_string(), "Max Level".to_string(), "Max Level With Undiscovered".to_string(), "PMs".to_string()]);
+    for state in states {
+        for building in state_buildings(data, &state)? {
+            grid.push(vec![
+                data.localize(&state),
+                data.localize(building.get_building()),
+                building.get_limit().name().to_string(),
+                level(building.get_max_level()),
+                level(building.get_potential_level()),
+                building.get_pms().iter().map(|pm| data.localize(pm)).collect::<Vec<String>>().join(", "),
+            ]);
+        }
+    }
+    write_grid("states.csv", grid);
+    Ok(())
+}
+
+// The building group of every building with its parents, and what limits how many levels a state can have
+fn write_buildings(data: &Data) {
+    let groups = data.get_building_groups();
+    let mut buildings = data.get_all_buildings();
+    buildings.sort_by(|a, b| groups.get_path(a.get_building_group()).into_iter().rev().cmp(groups.get_path(b.get_building_group()).into_iter().rev()).then(a.name().cmp(b.name())));
+
+    let yes_no = |value: bool| if value { "yes" } else { "no" }.to_string();
+    let mut grid: Vec<Vec<String>> = Vec::new();
+    grid.push(vec!["Building".to_string(), "Group".to_string(), "Group Path".to_string(), "Category".to_string(), "Land Usage".to_string(), "Subsistence".to_string(), "Buildable".to_string(), "Expandable".to_string(), "Arable Land".to_string(), "Resources".to_string()]);
+    for building in buildings {
+        let group = building.get_building_group();
+        grid.push(vec![
+            data.localize(building.name()),
+            data.localize(group),
+            groups.get_path(group).into_iter().rev().collect::<Vec<String>>().join(" > "),
+            groups.get_category(group).cloned().unwrap_or_default(),
+            groups.get_land_usage(group).cloned().unwrap_or_default(),
+            yes_no(building.is_subsistence(data)),
+            yes_no(building.is_buildable()),
+            yes_no(building.is_expandable()),
+            yes_no(building.needs_arable_land(data)),
+            yes_no(building.needs_resources()),
+        ]);
+    }
+    write_grid("buildings.csv", grid);
+}
+
+// What pops of each wealth buy every week and whether buildings use the same goods
+fn write_pop_needs(data: &Data) {
+    let pop_needs = data.get_pop_needs();
+    let building_inputs: Vec<String> = data.get_all_pms().iter().flat_map(|pm| pm.get_inputs().keys().cloned()).collect();
+
+    let mut grid: Vec<Vec<String>> = Vec::new();
+    grid.push(vec!["Wealth".to_string(), "Good".to_string(), format!("Amount Per {} Pops", PACKAGE_POPULATION), "Amount Per Pop".to_string(), "Needs".to_string(), "Used By Buildings".to_string()]);
+    for wealth in pop_needs.get_wealth_levels() {
+        for (good, amount) in pop_needs.demand(wealth) {
+            grid.push(vec![
+                wealth.to_string(),
+                data.localize(&good),
+                amount.to_string(),
+                (amount / PACKAGE_POPULATION).to_string(),
+                pop_needs.needs_for(wealth, &good).iter().map(|need| data.localize(need)).collect::<Vec<String>>().join(", "),
+                if building_inputs.contains(&good) { "yes" } else { "no" }.to_string(),
+            ]);
+        }
+    }
+    write_grid("pop_needs.csv", grid);
+}
+
 // Goes through each PM of each building 
 // Then writes the EfficiencyData of each PM to a CSV file
 fn write_csv(data: &Data) {
     let mut grid: Vec<Vec<String>>  = Vec::new();
-    grid.push(vec!["Building".to_string(), "PM".to_string(), "Input".to_string(), "Output".to_string(), "Labor".to_string(), "NetOutput".to_string(), "Efficiency Per Hundred Worker".to_string(), "EfficiencyPerConstruction".to_string()]);
+    grid.push(vec!["Building".to_string(), "PM".to_string(), "Input".to_string(), "Output".to_string(), "Labor".to_string(), "NetOutput".to_string(), "Efficiency Per Hundred Worker".to_string(), "EfficiencyPerConstruction".to_string(), "Wage Cost".to_string(), "Profit Per Wage".to_string(), "Employment".to_string(), "Goods".to_string()]);
     let mut new_building;
     for building in data.get_all_buildings() {
         new_building = true;
@@ -30,14 +290,18 @@ fn write_csv(data: &Data) {
 
                 if let Some(pm_data) = building.get_pm_data(data, &pm) {
                     grid.push(vec![
-                        if new_building { building.name().clone()  } else { "".to_owned() }, 
-                        { if i == 0 {""} else { "    |"} }.to_owned() + &pm.clone(),
+                        if new_building { data.localize(building.name()) } else { "".to_owned() }, 
+                        { if i == 0 {""} else { "    |"} }.to_owned() + &data.localize(pm),
                         pm_data.get(Input).to_string(), 
                         pm_data.get(Output).to_string(), 
                         pm_data.get(Labor).to_string(), 
                         pm_data.get(NetOutput).to_string(), 
                         (pm_data.get(EfficiencyPerWorker) * 100.0).to_string(), 
-                        pm_data.get(EfficiencyPerConstruction).to_string()]
+                        pm_data.get(EfficiencyPerConstruction).to_string(),
+                        pm_data.get(WageCost).to_string(),
+                        pm_data.try_get(ProfitPerWage).map(|v| v.to_string()).unwrap_or_default(),
+                        employment_summary(pm_data.get_employment()),
+                        pm_data.goods_summary(|good| data.localize(good))]
                     );
                     new_building = false;
                 }
@@ -45,11 +309,317 @@ fn write_csv(data: &Data) {
         }
     }
 
+    write_grid("output.csv", grid);
+}
+
+// Compares the PMs used in a save with the most efficient PM of their group
+// Each row is a PM that is used by at least one building in the save
+fn write_save_comparison(data: &Data, usage: &SaveUsage) {
+    let mut grid: Vec<Vec<String>> = Vec::new();
+    grid.push(vec!["Building".to_string(), "PM".to_string(), "Levels".to_string(), "Share Of Building Levels".to_string(), "Countries".to_string(), "Efficiency Per Hundred Worker".to_string(), "Best PM".to_string(), "Best Efficiency Per Hundred Worker".to_string()]);
+
+    let pm_levels = usage.get_pm_levels();
+    let pm_countries = usage.get_pm_countries();
+    let building_levels = usage.get_building_levels();
+
+    let mut used: Vec<&(String, String)> = pm_levels.keys().collect();
+    used.sort();
+    for key in used {
+        let (building_name, pm) = key;
+        let Some(building) = data.get_building(building_name) else {
+            continue;
+        };
+        let Some(pm_data) = building.get_pm_data(data, pm) else {
+            continue;
+        };
+
+        // The best PM among the ones of the same group
+        let mut best = (pm.clone(), pm_data.get(EfficiencyPerWorker));
+        for (_, pms) in building.get_pm_by_pmgs(data).into_iter().filter(|(_, pms)| pms.contains(pm)) {
+            for other in pms {
+                if let Some(other_data) = building.get_pm_data(data, &other) {
+                    if other_data.get(EfficiencyPerWorker) > best.1 {
+                        best = (other, other_data.get(EfficiencyPerWorker));
+                    }
+                }
+            }
+        }
+
+        let levels = pm_levels[key];
+        grid.push(vec![
+            data.localize(building_name),
+            data.localize(pm),
+            levels.to_string(),
+            (levels / building_levels.get(building_name).copied().unwrap_or(levels)).to_string(),
+            pm_countries.get(key).map(|c| c.len()).unwrap_or(0).to_string(),
+            (pm_data.get(EfficiencyPerWorker) * 100.0).to_string(),
+            data.localize(&best.0),
+            (best.1 * 100.0).to_string(),
+        ]);
+    }
+
+    write_grid("save_comparison.csv", grid);
+}
+
+// Every combination of PMs of each building, one PM per group
+// The Pareto optimal column marks the combinations that no other combination of the building beats
+// both in efficiency per worker and in efficiency per construction
+fn write_combinations(data: &Data) {
+    let mut grid: Vec<Vec<String>> = Vec::new();
+    grid.push(vec!["Building".to_string(), "PMs".to_string(), "NetOutput".to_string(), "Efficiency Per Hundred Worker".to_string(), "EfficiencyPerConstruction".to_string(), "Profit Per Wage".to_string(), "Pareto Optimal".to_string()]);
+
+    let mut buildings = data.get_all_buildings();
+    buildings.sort_by(|a, b| a.name().cmp(b.name()));
+    for building in buildings {
+        let combinations: Vec<CombinationRow> = building.get_combinations(data).into_iter()
+            .filter_map(|pms| {
+                let pm_data = building.get_combination_data(data, &pms)?;
+                Some((pms, pm_data.get(NetOutput), comparable(pm_data.get(EfficiencyPerWorker)), comparable(pm_data.get(EfficiencyPerConstruction)), pm_data.try_get(ProfitPerWage)))
+            })
+            .collect();
+
+        for (pms, net_output, per_worker, per_construction, per_wage) in &combinations {
+            let dominated = is_dominated((*per_worker, *per_construction), combinations.iter().map(|(_, _, worker, construction, _)| (*worker, *construction)));
+            grid.push(vec![
+                data.localize(building.name()),
+                pms.iter().map(|pm| data.localize(pm)).collect::<Vec<String>>().join(" + "),
+                net_output.to_string(),
+                (per_worker * 100.).to_string(),
+                per_construction.to_string(),
+                per_wage.map(|v| v.to_string()).unwrap_or_default(),
+                if dominated { "no" } else { "yes" }.to_string(),
+            ]);
+        }
+    }
+
+    write_grid("combinations.csv", grid);
+}
+
+// PMs, net output, efficiency per worker, efficiency per construction, profit per wage
+type CombinationRow = (Vec<String>, f32, f32, f32, Option<f32>);
+
+// Whether one of the others is at least as good in both (efficiency per worker, efficiency per construction)
+// and better in one of them, the same values on both sides don't count
+fn is_dominated(point: (f32, f32), others: impl IntoIterator<Item = (f32, f32)>) -> bool {
+    others.into_iter().any(|other| other.0 >= point.0 && other.1 >= point.1 && (other.0 > point.0 || other.1 > point.1))
+}
+
+// NaN (e.g. no workers) can't be compared, it's counted as the worst value
+fn comparable(value: f32) -> f32 {
+    if value.is_nan() { f32::NEG_INFINITY } else { value }
+}
+
+// For each group of PMs and each good its PMs use or make, moves the price of the good from -75% to +75%
+// and writes the price ranges in which each PM is the most efficient per worker of its group
+// Only the goods that change which PM is the best are written
+fn write_sensitivity(data: &mut Data) {
+    const STEPS: usize = 30;
+    let mut grid: Vec<Vec<String>> = Vec::new();
+    grid.push(vec!["Building".to_string(), "PMG".to_string(), "Good".to_string(), "Best PM".to_string(), "From Price".to_string(), "To Price".to_string(), "From % Of Base".to_string(), "To % Of Base".to_string()]);
+
+    let scenario = data.get_goods().get_scenario().clone();
+    let mut building_names: Vec<String> = data.get_all_buildings().iter().map(|b| b.name().clone()).collect();
+    building_names.sort();
+    for building_name in building_names {
+        let groups = data.get_building(&building_name).map(|b| b.get_pm_by_pmgs(data)).unwrap_or_default();
+        for (pmg, pms) in groups.into_iter().filter(|(_, pms)| pms.len() > 1) {
+            let mut goods: Vec<String> = pms.iter()
+                .filter_map(|pm| data.get_pm(pm))
+                .flat_map(|pm| pm.get_inputs().keys().chain(pm.get_outputs().keys()).cloned().collect::<Vec<String>>())
+                .collect();
+            goods.sort();
+            goods.dedup();
+
+            for good in goods {
+                let base_cost = data.get_cost(&good) as f32;
+                if base_cost <= 0. {
+                    continue;
+                }
+                // (price factor, best PM at that price)
+                let mut steps: Vec<(f32, String)> = Vec::new();
+                for step in 0..=STEPS {
+                    let factor = MIN_PRICE_FACTOR + (MAX_PRICE_FACTOR - MIN_PRICE_FACTOR) * step as f32 / STEPS as f32;
+                    let mut step_scenario = scenario.clone();
+                    step_scenario.set_override(&good, base_cost * factor);
+                    // Only the PMs of the group are compared, valuing every other PM again 31 times is wasted
+                    data.set_price_scenario_for(step_scenario, &pms);
+
+                    let Some(building) = data.get_building(&building_name) else {
+                        continue;
+                    };
+                    let best = pms.iter()
+                        .filter_map(|pm| Some((pm, building.get_pm_data(data, pm)?.get(EfficiencyPerWorker))))
+                        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+                    if let Some((pm, _)) = best {
+                        steps.push((factor, pm.clone()));
+                    }
+                }
+                if steps.iter().all(|(_, pm)| *pm == steps[0].1) {
+                    continue;
+                }
+
+                // One row per range of prices with the same best PM
+                let mut start = 0;
+                for i in 1..=steps.len() {
+                    if i == steps.len() || steps[i].1 != steps[start].1 {
+                        let (from, to) = (steps[start].0, steps[i - 1].0);
+                        grid.push(vec![
+                            data.localize(&building_name),
+                            data.localize(&pmg),
+                            data.localize(&good),
+                            data.localize(&steps[start].1),
+                            (base_cost * from).to_string(),
+                            (base_cost * to).to_string(),
+                            format!("{:.0}", from * 100.),
+                            format!("{:.0}", to * 100.),
+                        ]);
+                        start = i;
+                    }
+                }
+            }
+        }
+    }
+    data.set_price_scenario(scenario);
+
+    write_grid("sensitivity.csv", grid);
+}
+
+// e.g. 2000 laborers, 500 engineers
+fn employment_summary(employment: &HashMap<String, f32>) -> String {
+    let mut professions: Vec<(&String, &f32)> = employment.iter().filter(|(_, amount)| **amount != 0.).collect();
+    professions.sort_by(|a, b| a.0.cmp(b.0));
+    professions.iter().map(|(profession, amount)| format!("{} {}", amount, profession)).collect::<Vec<String>>().join(", ")
+}
+
+// Compares two versions of the game, e.g. before and after a patch
+// patch_diff.csv has a row per building and PM that changed, with the old value, new value and delta of every attribute
+// The efficiency rank column is filled when the PM moved in the ranking of its group
+// patch_goods.csv lists the goods whose cost changed
+fn write_patch_diff(old: &Data, new: &Data) {
+    let mut header = vec!["Building".to_string(), "PM".to_string(), "Status".to_string(), "Efficiency Rank".to_string()];
+    for attribute in Attribute::ALL {
+        header.push(format!("{} Old", attribute.name()));
+        header.push(format!("{} New", attribute.name()));
+        header.push(format!("{} Delta", attribute.name()));
+    }
+    let mut grid: Vec<Vec<String>> = vec![header];
+
+    let old_ranks = efficiency_ranks(old);
+    let new_ranks = efficiency_ranks(new);
+    let mut keys: Vec<&(String, String)> = old_ranks.keys().chain(new_ranks.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    for key in keys {
+        let (building_name, pm) = key;
+        let old_data = old.get_building(building_name).and_then(|b| b.get_pm_data(old, pm));
+        let new_data = new.get_building(building_name).and_then(|b| b.get_pm_data(new, pm));
+        let rank = match (old_ranks.get(key), new_ranks.get(key)) {
+            (Some(old_rank), Some(new_rank)) if old_rank != new_rank => format!("{} -> {}", old_rank, new_rank),
+            _ => String::new(),
+        };
+
+        let mut changed = !rank.is_empty();
+        let mut values = Vec::new();
+        for attribute in Attribute::ALL {
+            let old_value = old_data.as_ref().and_then(|d| d.try_get(attribute));
+            let new_value = new_data.as_ref().and_then(|d| d.try_get(attribute));
+            changed |= differs(old_value, new_value);
+            values.push(old_value.map(|v| v.to_string()).unwrap_or_default());
+            values.push(new_value.map(|v| v.to_string()).unwrap_or_default());
+            values.push(match (old_value, new_value) {
+                (Some(old_value), Some(new_value)) => (new_value - old_value).to_string(),
+                _ => String::new(),
+            });
+        }
+
+        let status = match (&old_data, &new_data) {
+            (None, Some(_)) => "Added",
+            (Some(_), None) => "Removed",
+            _ if changed => "Changed",
+            _ => continue,
+        };
+        let mut row = vec![new.localize(building_name), new.localize(pm), status.to_string(), rank];
+        row.extend(values);
+        grid.push(row);
+    }
+    write_grid("patch_diff.csv", grid);
+
+    let mut goods_grid: Vec<Vec<String>> = vec![vec!["Good".to_string(), "Old Cost".to_string(), "New Cost".to_string(), "Delta".to_string()]];
+    let mut goods = old.get_goods_names();
+    goods.extend(new.get_goods_names());
+    goods.sort();
+    goods.dedup();
+    for good in goods {
+        let (old_cost, new_cost) = (old.get_cost(&good), new.get_cost(&good));
+        if old_cost != new_cost {
+            goods_grid.push(vec![new.localize(&good), old_cost.to_string(), new_cost.to_string(), (new_cost - old_cost).to_string()]);
+        }
+    }
+    write_grid("patch_goods.csv", goods_grid);
+}
+
+// (building, pm) -> rank of the PM in its group by efficiency per worker, starting at 1
+fn efficiency_ranks(data: &Data) -> HashMap<(String, String), usize> {
+    let mut ranks = HashMap::new();
+    for building in data.get_all_buildings() {
+        for (_, pms) in building.get_pm_by_pmgs(data) {
+            let mut efficiencies: Vec<(String, f32)> = pms.into_iter()
+                .filter_map(|pm| {
+                    let efficiency = building.get_pm_data(data, &pm)?.get(EfficiencyPerWorker);
+                    Some((pm, efficiency))
+                })
+                .collect();
+            efficiencies.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            for (i, (pm, _)) in efficiencies.into_iter().enumerate() {
+                ranks.insert((building.name().clone(), pm), i + 1);
+            }
+        }
+    }
+    ranks
+}
+
+// Small float differences are ignored, NaN (e.g. no workers) is equal to NaN
+fn differs(old: Option<f32>, new: Option<f32>) -> bool {
+    match (old, new) {
+        (Some(old), Some(new)) => !(old == new || (old.is_nan() && new.is_nan()) || (old - new).abs() < 1e-4),
+        (None, None) => false,
+        _ => true,
+    }
+}
+
+fn write_grid(path: &str, grid: Vec<Vec<String>>) {
     // write csv raw (without external crate)
-    let mut file = std::fs::File::create("output.csv").unwrap();
+    let mut file = std::fs::File::create(path).unwrap();
     for row in grid {
         let mut row = row.iter().map(|s| format!("\"{}\"", s)).collect::<Vec<String>>().join(",");
         row.push('\n');
         file.write_all(row.as_bytes()).unwrap();
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pareto_dominance() {
+        let points = [(2., 1.), (1., 2.), (1., 1.), (2., 1.)];
+        assert!(!is_dominated((2., 1.), points));
+        assert!(!is_dominated((1., 2.), points));
+        assert!(is_dominated((1., 1.), points));
+        // Ties in one value are beaten by the other one
+        assert!(is_dominated((2., 0.5), points));
+        assert!(!is_dominated((0.5, 3.), points));
+    }
+
+    #[test]
+    fn nan_is_worst() {
+        let points = [(comparable(f32::NAN), 5.), (0., 5.)];
+        assert!(is_dominated(points[0], points));
+        assert!(!is_dominated(points[1], points));
+        assert!(!is_dominated((comparable(f32::NAN), 6.), points));
+        // Two NaN are a tie
+        assert!(!is_dominated((comparable(f32::NAN), 1.), [(comparable(f32::NAN), 1.)]));
+    }
+}
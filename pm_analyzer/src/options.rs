@@ -0,0 +1,150 @@
+use vic3_parser::localization::{DEFAULT_LANGUAGE, LANGUAGES};
+
+// Command line options
+// Usage: pm-analyzer [--language <language>] [--missing-localization] [--save <path>] [--diff-vanilla] [--patch-diff <old game> <new game>] [--wages <path>]
+//   [--price-shift <percent>] [--prices <path>] [--save-prices <path>] [--sensitivity] [--combinations] [--techs <tech,tech...>] [--era <number>] [--research <pm>] [--country <tag>] [--supply-chain]
+//   [--true-cost] [--pms <pm,pm...>] [--balance <scenario>] [--pop-needs]
+//   [--groups <group,group...>] [--buildings] [--state-report] [--states <state,state...>]
+pub struct Options {
+    pub language: String,
+    // Writes the missing localization report instead of the spreadsheet
+    pub missing_localization: bool,
+    // Plaintext save to compare the PMs in use with the theoretical ones
+    pub save: Option<String>,
+    // Writes what the mod changes in the vanilla PMs
+    pub diff_vanilla: bool,
+    // Two game installs to compare, e.g. before and after a patch
+    pub patch_diff: Option<(String, String)>,
+    // File of profession = wage lines overriding the default wages
+    pub wages: Option<String>,
+    // Moves every price by this percentage of the base cost, e.g. -20
+    pub price_shift: f32,
+    // File of good = price lines
+    pub prices: Option<String>,
+    // Plaintext save to take the market prices from
+    pub save_prices: Option<String>,
+    // Writes at what price each PM becomes the best of its group instead of the spreadsheet
+    pub sensitivity: bool,
+    // Writes every combination of PMs of each building instead of the spreadsheet
+    pub combinations: bool,
+    // Researched technologies, their prerequisites are added automatically
+    pub techs: Vec<String>,
+    // Every technology up to this era is researched
+    pub era: Option<u32>,
+    // Writes the technologies needed to unlock this PM instead of the spreadsheet
+    pub research: Option<String>,
+    // Only the PMs this country can pick with its starting laws and technologies
+    pub country: Option<String>,
+    // Writes the graph of which PMs make and use which goods instead of the spreadsheet
+    pub supply_chain: bool,
+    // Writes how many workers and how much construction each good takes with its whole supply chain
+    pub true_cost: bool,
+    // PMs --true-cost uses instead of the default PM of their group
+    pub pms: Vec<String>,
+    // YAML or JSON file of buildings to simulate the market of instead of writing the spreadsheet
+    pub balance: Option<String>,
+    // Writes what pops of each wealth buy instead of the spreadsheet
+    pub pop_needs: bool,
+    // Only the buildings of these building groups and their children, e.g. bg_mining
+    pub groups: Vec<String>,
+    // Writes the building group, land and resources of every building instead of the spreadsheet
+    pub buildings: bool,
+    // Writes the buildings the states have room for instead of the spreadsheet
+    pub state_report: bool,
+    // States for --state-report, the states of --country when there are none
+    pub states: Vec<String>,
+}
+
+impl Options {
+    pub fn from_args() -> Result<Options, String> {
+        Options::parse(std::env::args().skip(1).collect())
+    }
+
+    pub fn parse(args: Vec<String>) -> Result<Options, String> {
+        let mut options = Options {
+            language: DEFAULT_LANGUAGE.to_string(),
+            missing_localization: false,
+            save: None,
+            diff_vanilla: false,
+            patch_diff: None,
+            wages: None,
+            price_shift: 0.,
+            prices: None,
+            save_prices: None,
+            sensitivity: false,
+            combinations: false,
+            techs: Vec::new(),
+            era: None,
+            research: None,
+            country: None,
+            supply_chain: false,
+            true_cost: false,
+            pms: Vec::new(),
+            balance: None,
+            pop_needs: false,
+            groups: Vec::new(),
+            buildings: false,
+            state_report: false,
+            states: Vec::new(),
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--language" | "-l" => {
+                    let language = args.next().ok_or("Missing value for --language")?;
+                    if !LANGUAGES.contains(&language.as_str()) {
+                        return Err(format!("Unknown language {}, expected one of: {}", language, LANGUAGES.join(", ")));
+                    }
+                    options.language = language;
+                }
+                "--missing-localization" => options.missing_localization = true,
+                "--save" => options.save = Some(args.next().ok_or("Missing value for --save")?),
+                "--wages" => options.wages = Some(args.next().ok_or("Missing value for --wages")?),
+                "--price-shift" => {
+                    let shift = args.next().ok_or("Missing value for --price-shift")?;
+                    options.price_shift = shift.trim_end_matches('%').parse().map_err(|_| format!("Invalid price shift {}", shift))?;
+                }
+                "--prices" => options.prices = Some(args.next().ok_or("Missing value for --prices")?),
+                "--save-prices" => options.save_prices = Some(args.next().ok_or("Missing value for --save-prices")?),
+                "--sensitivity" => options.sensitivity = true,
+                "--combinations" => options.combinations = true,
+                "--supply-chain" => options.supply_chain = true,
+                "--true-cost" => options.true_cost = true,
+                "--pop-needs" => options.pop_needs = true,
+                "--buildings" => options.buildings = true,
+                "--state-report" => options.state_report = true,
+                "--states" => {
+                    let states = args.next().ok_or("Missing value for --states")?;
+                    options.states.extend(states.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()));
+                }
+                "--groups" => {
+                    let groups = args.next().ok_or("Missing value for --groups")?;
+                    options.groups.extend(groups.split(',').map(|g| g.trim().to_string()).filter(|g| !g.is_empty()));
+                }
+                "--pms" => {
+                    let pms = args.next().ok_or("Missing value for --pms")?;
+                    options.pms.extend(pms.split(',').map(|pm| pm.trim().to_string()).filter(|pm| !pm.is_empty()));
+                }
+                "--techs" => {
+                    let techs = args.next().ok_or("Missing value for --techs")?;
+                    options.techs.extend(techs.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()));
+                }
+                "--era" => {
+                    let era = args.next().ok_or("Missing value for --era")?;
+                    options.era = Some(era.trim_start_matches("era_").parse().map_err(|_| format!("Invalid era {}", era))?);
+                }
+                "--balance" => options.balance = Some(args.next().ok_or("Missing value for --balance")?),
+                "--research" => options.research = Some(args.next().ok_or("Missing value for --research")?),
+                "--country" => options.country = Some(args.next().ok_or("Missing value for --country")?.trim_start_matches("c:").to_string()),
+                "--diff-vanilla" => options.diff_vanilla = true,
+                "--patch-diff" => {
+                    let old = args.next().ok_or("Missing old game path for --patch-diff")?;
+                    let new = args.next().ok_or("Missing new game path for --patch-diff")?;
+                    options.patch_diff = Some((old, new));
+                }
+                _ => return Err(format!("Unknown argument {}", arg)),
+            }
+        }
+        Ok(options)
+    }
+}
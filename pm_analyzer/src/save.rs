@@ -0,0 +1,252 @@
+use std::{collections::HashMap, io::Read};
+
+use vic3_parser::{save::SaveFile, Event, EventReader, Tree};
+
+// A building found in a save, with the PMs it currently uses
+#[derive(Debug)]
+pub struct SavedBuilding {
+    building: String,
+    state: String,
+    levels: f32,
+    pms: Vec<String>,
+}
+
+// What the countries of a save actually use
+// Only the buildings, states and countries are read, everything else in the gamestate is skipped
+#[derive(Debug)]
+pub struct SaveUsage {
+    buildings: Vec<SavedBuilding>,
+    // state id -> country id
+    states: HashMap<String, String>,
+    // country id -> country tag
+    countries: HashMap<String, String>,
+}
+
+impl SaveUsage {
+    pub fn from_save(path: &str) -> Result<SaveUsage, String> {
+        let save = SaveFile::open(path)?;
+        let mut events = save.gamestate()?;
+        let mut usage = SaveUsage {
+            buildings: Vec::new(),
+            states: HashMap::new(),
+            countries: HashMap::new(),
+        };
+
+        while let Some(event) = events.next_event()? {
+            match event {
+                Event::BeginBlock(key) if key == "building_manager" => {
+                    read_database(&mut events, |_, tree| {
+                        if let Some(building) = SavedBuilding::from_tree(&tree) {
+                            usage.buildings.push(building);
+                        }
+                    })?;
+                }
+                Event::BeginBlock(key) if key == "states" => {
+                    read_database(&mut events, |id, tree| {
+                        if let Ok(country) = tree.get("country").and_then(|c| c.value()) {
+                            usage.states.insert(id.to_string(), country);
+                        }
+                    })?;
+                }
+                Event::BeginBlock(key) if key == "country_manager" => {
+                    read_database(&mut events, |id, tree| {
+                        if let Ok(tag) = tree.get("definition").and_then(|c| c.value()) {
+                            usage.countries.insert(id.to_string(), unquote(&tag));
+                        }
+                    })?;
+                }
+                Event::BeginBlock(_) => events.skip_block()?,
+                _ => {}
+            }
+        }
+        Ok(usage)
+    }
+
+    pub fn get_buildings(&self) -> &Vec<SavedBuilding> {
+        &self.buildings
+    }
+
+    // Tag of the country that owns the state of the building
+    pub fn get_country(&self, building: &SavedBuilding) -> Option<&String> {
+        self.countries.get(self.states.get(&building.state)?)
+    }
+
+    // (building, pm) -> total levels using it
+    pub fn get_pm_levels(&self) -> HashMap<(String, String), f32> {
+        let mut levels = HashMap::new();
+        for building in &self.buildings {
+            for pm in &building.pms {
+                *levels.entry((building.building.clone(), pm.clone())).or_insert(0.) += building.levels;
+            }
+        }
+        levels
+    }
+
+    // (building, pm) -> tags of the countries using it
+    pub fn get_pm_countries(&self) -> HashMap<(String, String), Vec<String>> {
+        let mut countries: HashMap<(String, String), Vec<String>> = HashMap::new();
+        for building in &self.buildings {
+            let Some(country) = self.get_country(building) else {
+                continue;
+            };
+            for pm in &building.pms {
+                let entry = countries.entry((building.building.clone(), pm.clone())).or_default();
+                if !entry.contains(country) {
+                    entry.push(country.clone());
+                }
+            }
+        }
+        countries
+    }
+
+    // building -> total levels in the save
+    pub fn get_building_levels(&self) -> HashMap<String, f32> {
+        let mut levels = HashMap::new();
+        for building in &self.buildings {
+            *levels.entry(building.building.clone()).or_insert(0.) += building.levels;
+        }
+        levels
+    }
+}
+
+impl SavedBuilding {
+    fn from_tree(tree: &Tree) -> Option<SavedBuilding> {
+        let building = unquote(&tree.get("building").ok()?.value().ok()?);
+        let state = tree.get("state").and_then(|s| s.value()).unwrap_or_default();
+        let levels = ["levels", "level"].iter()
+            .find_map(|key| tree.get(*key).ok()?.value().ok()?.parse::<f32>().ok())
+            .unwrap_or(1.);
+        let pms = tree.get("production_methods")
+            .map(|pms| pms.get_children_names().iter().map(|pm| unquote(pm)).collect())
+            .unwrap_or_default();
+        Some(SavedBuilding { building, state, levels, pms })
+    }
+
+    pub fn name(&self) -> &String {
+        &self.building
+    }
+
+    pub fn get_levels(&self) -> f32 {
+        self.levels
+    }
+
+    pub fn get_pms(&self) -> &Vec<String> {
+        &self.pms
+    }
+}
+
+// Average price of each good over the markets of a save
+// Each market of market_manager has a prices block, either good = price or a list of prices in the order of the goods
+pub fn read_market_prices(path: &str, goods_order: &[String]) -> Result<HashMap<String, f32>, String> {
+    let save = SaveFile::open(path)?;
+    let mut events = save.gamestate()?;
+    let mut totals: HashMap<String, (f32, usize)> = HashMap::new();
+    while let Some(event) = events.next_event()? {
+        match event {
+            Event::BeginBlock(key) if key == "market_manager" => {
+                read_database(&mut events, |_, tree| {
+                    let Ok(prices) = tree.get("prices") else {
+                        return;
+                    };
+                    for (good, price) in market_prices(&prices, goods_order) {
+                        let total = totals.entry(good).or_insert((0., 0));
+                        total.0 += price;
+                        total.1 += 1;
+                    }
+                })?;
+            }
+            Event::BeginBlock(_) => events.skip_block()?,
+            _ => {}
+        }
+    }
+    if totals.is_empty() {
+        return Err("No market prices in the save".to_string());
+    }
+    Ok(totals.into_iter().map(|(good, (total, count))| (good, total / count as f32)).collect())
+}
+
+fn market_prices(prices: &Tree, goods_order: &[String]) -> Vec<(String, f32)> {
+    let names = prices.get_children_names();
+    // Bare list of prices, one per good
+    let is_list = names.iter().all(|name| prices.get(name).map_or(true, |price| price.value().is_err()));
+    if is_list {
+        return goods_order.iter().cloned().zip(names.iter().filter_map(|price| price.parse::<f32>().ok())).collect();
+    }
+    let mut result = Vec::new();
+    for name in names {
+        let Some(price) = prices.get(&name).ok().and_then(|p| p.value().ok()).and_then(|p| p.parse::<f32>().ok()) else {
+            continue;
+        };
+        // Goods are either named or referred to by their index
+        let good = match name.parse::<usize>() {
+            Ok(index) => goods_order.get(index).cloned(),
+            Err(_) => Some(unquote(&name)),
+        };
+        if let Some(good) = good {
+            result.push((good, price));
+        }
+    }
+    result
+}
+
+// Goes through the database block of a manager, e.g. building_manager = { database = { 1 = { ... } 2 = none } }
+// Call this right after the BeginBlock of the manager
+fn read_database<R: Read>(events: &mut EventReader<R>, mut f: impl FnMut(&str, Tree)) -> Result<(), String> {
+    let depth = events.depth();
+    while let Some(event) = events.next_event()? {
+        match event {
+            Event::BeginBlock(key) if key == "database" && events.depth() == depth + 1 => {}
+            Event::BeginBlock(id) if events.depth() == depth + 2 => {
+                let entry = events.read_block(&id)?;
+                f(&id, entry);
+            }
+            Event::BeginBlock(_) => events.skip_block()?,
+            Event::EndBlock if events.depth() < depth => break,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn unquote(value: &str) -> String {
+    value.trim_matches('"').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> String {
+        format!("{}/tests/fixtures/usage.v3", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    #[test]
+    fn buildings_and_pms() {
+        let usage = SaveUsage::from_save(&fixture()).unwrap();
+        // 101 = none is a removed building
+        let buildings: Vec<(&String, f32)> = usage.get_buildings().iter().map(|b| (b.name(), b.get_levels())).collect();
+        assert_eq!(buildings, [(&"building_steel_mills".to_string(), 3.), (&"building_steel_mills".to_string(), 2.), (&"building_iron_mine".to_string(), 1.)]);
+
+        let levels = usage.get_building_levels();
+        assert_eq!(levels["building_steel_mills"], 5.);
+        assert_eq!(levels["building_iron_mine"], 1.);
+
+        let pm = |building: &str, pm: &str| (building.to_string(), pm.to_string());
+        let pm_levels = usage.get_pm_levels();
+        assert_eq!(pm_levels[&pm("building_steel_mills", "pm_bessemer_process")], 3.);
+        assert_eq!(pm_levels[&pm("building_steel_mills", "pm_blister_steel_process")], 2.);
+        assert_eq!(pm_levels[&pm("building_steel_mills", "pm_no_automation")], 5.);
+
+        let pm_countries = usage.get_pm_countries();
+        assert_eq!(pm_countries[&pm("building_steel_mills", "pm_bessemer_process")], ["GBR"]);
+        assert_eq!(pm_countries[&pm("building_steel_mills", "pm_no_automation")], ["GBR", "FRA"]);
+        assert_eq!(pm_countries[&pm("building_iron_mine", "pm_picks_and_shovels")], ["FRA"]);
+    }
+
+    #[test]
+    fn average_market_prices() {
+        let prices = read_market_prices(&fixture(), &[]).unwrap();
+        assert_eq!(prices["steel"], 55.);
+        assert_eq!(prices["iron"], 38.);
+    }
+}
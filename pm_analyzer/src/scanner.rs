@@ -1,31 +1,122 @@
 use std::{ffi::OsString, fs, io::Write, path::PathBuf};
-use vic3_parser::{Parser, Tree, utils::*};
-use crate::data::Data;
+use vic3_parser::{Parser, Tree, utils::*, diff::{diff, Diff}, localization::{self, Localization}};
+use vic3_game_data::GameData;
+use std::collections::HashSet;
 
-pub fn scan() -> Result<Data, String> { 
+use crate::{data::{CountrySetup, Data, Laws, PriceScenario, Wages}, options::Options, save};
+
+pub fn scan(options: &Options) -> Result<Data, String> { 
     let (mod_path, game_path) = get_scan_paths();
+    scan_paths(options, mod_path, game_path)
+}
 
+// Scans a game install on its own, without any mod
+pub fn scan_game(options: &Options, game_path: &str) -> Result<Data, String> {
+    scan_paths(options, game_path.to_string(), game_path.to_string())
+}
 
-    let pm_files = get_paths(&mod_path, &game_path, r#"\common\production_methods"#);
-    let goods_files = get_paths(&mod_path, &game_path, r#"\common\goods"#);
-    let pmg_files = get_paths(&mod_path, &game_path, r#"\common\production_method_groups"#);
-    let buildings_files = get_paths(&mod_path, &game_path, r#"\common\buildings"#);
+fn scan_paths(options: &Options, mod_path: String, game_path: String) -> Result<Data, String> {
 
-    let pm_tree = generate_tree(pm_files);
+    let game = GameData::new(mod_path.clone(), game_path.clone());
 
-    let goods_tree = generate_tree(goods_files);
+    for issue in game.get_technologies().validate() {
+        println!("Error in technologies: {}", issue);
+    }
 
-    let pmg_tree = generate_tree(pmg_files);
+    let localization = Localization::load(&mod_path, &game_path, &options.language);
 
-    let buildings_tree = generate_tree(buildings_files);
+    let wages = match &options.wages {
+        Some(path) => Wages::from_file(path)?,
+        None => Wages::default(),
+    };
+
+    let mut data = Data::new(&game, localization, wages)?;
+    let mut active_laws = None;
+    if let Some(tag) = &options.country {
+        let (country, laws) = read_country(&game, tag)?;
+        data.set_country(country);
+        active_laws = Some(laws);
+    }
+    // --research needs every PM, what is already researched is left out of its answer instead
+    if options.research.is_none() {
+        if let Some(unlocked) = unlocked_technologies(options, &data) {
+            data.restrict_to_technologies(&unlocked);
+        }
+        if let Some(laws) = active_laws {
+            data.restrict_to_laws(&laws);
+        }
+    }
+    if !options.groups.is_empty() {
+        data.restrict_to_groups(&options.groups);
+    }
+    Ok(data)
+}
+
+// The history of the country and the laws it enacts
+fn read_country(game: &GameData, tag: &str) -> Result<(CountrySetup, HashSet<String>), String> {
+    let history = game.get_country_setup(tag).ok_or(format!("Country {} not found in the history files", tag))?;
+    let setup = CountrySetup::new(tag, &history, game.get_country_states(tag));
+    let active_laws = Laws::from_database(game.get_laws()).active_laws(setup.get_laws());
+    Ok((setup, active_laws))
+}
+
+// The technologies researched according to --techs, --era and the history of --country, with their prerequisites
+// None when none of them is given, everything is available then
+pub fn unlocked_technologies(options: &Options, data: &Data) -> Option<HashSet<String>> {
+    let country_technologies = data.get_country().map(|country| country.get_technologies().clone()).unwrap_or_default();
+    if options.techs.is_empty() && options.era.is_none() && country_technologies.is_empty() {
+        return None;
+    }
+    let technologies = data.get_technologies();
+    let mut unlocked = technologies.closure(&options.techs);
+    unlocked.extend(technologies.closure(&country_technologies));
+    if let Some(era) = options.era {
+        unlocked.extend(technologies.up_to_era(era));
+    }
+    Some(unlocked)
+}
 
-    let building_values = format!("{}{}",  { if std::path::Path::new(&format!("{}{}", mod_path, r#"\common\script_values\building_values.txt"#)).exists() {
-        mod_path
-    } else {
-        game_path
-    } }, r#"\common\script_values\building_values.txt"#);
-    let building_values_tree = parse_file(&building_values);
 
-    Ok(Data::new(pm_tree, goods_tree, pmg_tree, buildings_tree, building_values_tree)?)
+// The prices asked for on the command line, applied in this order: global shift, save prices, prices file
+pub fn price_scenario(options: &Options, data: &Data) -> Result<PriceScenario, String> {
+    let mut scenario = PriceScenario::shifted(options.price_shift);
+    if let Some(path) = &options.save_prices {
+        scenario.extend(save::read_market_prices(path, data.get_goods().get_order())?);
+    }
+    if let Some(path) = &options.prices {
+        scenario.extend(PriceScenario::read_overrides(path)?);
+    }
+    Ok(scenario)
 }
 
+pub fn scan_all_localizations() -> Vec<Localization> {
+    let (mod_path, game_path) = get_scan_paths();
+    localization::load_all(&mod_path, &game_path)
+}
+
+// The PMs of the mod compared with the vanilla PMs of the same name
+// PMs that only exist in the mod show up as added, vanilla PMs the mod doesn't touch are left out
+pub fn diff_with_vanilla() -> Result<Diff, String> {
+    let (mod_path, game_path) = get_scan_paths();
+    let folder = r#"\common\production_methods"#;
+    let mod_tree = generate_tree(files_in(&format!("{}{}", mod_path, folder)));
+    let mut vanilla_tree = generate_tree(files_in(&format!("{}{}", game_path, folder)));
+
+    let mod_pms = mod_tree.get_children_names();
+    for handle in vanilla_tree.child_handles(|pm| !mod_pms.contains(pm)) {
+        vanilla_tree.remove(handle)?;
+    }
+    Ok(diff(&vanilla_tree, &mod_tree))
+}
+
+fn files_in(folder: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    entries.filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|ft| ft.is_file()))
+        // Same as discriminate, readme files and the like are not script files
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "txt"))
+        .map(|entry| entry.path().to_string_lossy().into_owned())
+        .collect()
+}